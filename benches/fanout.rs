@@ -0,0 +1,56 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use libonebot::{Event, Group, Message, SharedEvent, User};
+
+fn group_message_event() -> Event {
+    let message = Message::build("6283")
+        .text("这是一条足够长的群消息，用于模拟高频群聊中的普通文本消息。")
+        .mention("10002")
+        .image("file-id")
+        .reply("6282", "10002")
+        .group(Group::new("20001"), User::new("10001"));
+    Event::build("1")
+        .platform("test")
+        .bot_user(User::new("bot"))
+        .message(message)
+}
+
+// 将一个事件推送给 clients 个 WebSocket 连接，最终都需要得到 Text 帧使用的 String
+fn fanout(c: &mut Criterion) {
+    let event = group_message_event();
+    let mut group = c.benchmark_group("fanout");
+    for clients in [1, 10, 100, 1000] {
+        group.throughput(Throughput::Elements(clients));
+
+        // 每个连接各自复制并序列化事件
+        group.bench_with_input(
+            BenchmarkId::new("per_client", clients),
+            &clients,
+            |b, &clients| {
+                b.iter(|| {
+                    for _ in 0..clients {
+                        let event = event.clone();
+                        criterion::black_box(serde_json::to_string(&event).unwrap());
+                    }
+                })
+            },
+        );
+
+        // 序列化一次，各连接共享序列化结果
+        group.bench_with_input(
+            BenchmarkId::new("shared", clients),
+            &clients,
+            |b, &clients| {
+                b.iter(|| {
+                    let shared = SharedEvent::new(event.clone());
+                    for _ in 0..clients {
+                        criterion::black_box(shared.json_string().unwrap());
+                    }
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, fanout);
+criterion_main!(benches);
@@ -1,18 +1,164 @@
-use serde::Deserialize;
+use crate::{Error, Result};
+use async_trait::async_trait;
+use futures::FutureExt;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt::Display, future::Future, panic::AssertUnwindSafe, sync::Arc};
+
+#[async_trait]
+pub trait ActionHandler: Send + Sync {
+    async fn handle(&self, params: serde_json::Value) -> ActionResponse;
+}
+
+#[async_trait]
+impl<F, Fut> ActionHandler for F
+where
+    F: Fn(serde_json::Value) -> Fut + Send + Sync,
+    Fut: Future<Output = ActionResponse> + Send,
+{
+    async fn handle(&self, params: serde_json::Value) -> ActionResponse {
+        self(params).await
+    }
+}
 
 #[derive(Clone)]
 pub struct Action {
-    pub action: fn(_: serde_json::Value) -> String,
+    handler: Arc<dyn ActionHandler>,
+}
+
+impl Action {
+    pub fn new<H: 'static + ActionHandler>(handler: H) -> Self {
+        Self {
+            handler: Arc::new(handler),
+        }
+    }
+
+    pub async fn handle(&self, params: serde_json::Value) -> ActionResponse {
+        self.handler.handle(params).await
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ActionStatus {
+    Ok,
+    Failed,
 }
 
-impl From<fn(_: serde_json::Value) -> String> for Action {
-    fn from(action: fn(_: serde_json::Value) -> String) -> Self {
-        Self { action }
+#[derive(Debug, Clone, Serialize)]
+pub struct ActionResponse {
+    pub status: ActionStatus,
+    pub retcode: i64,
+    pub data: serde_json::Value,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub echo: Option<serde_json::Value>,
+}
+
+impl ActionResponse {
+    pub fn ok(data: serde_json::Value) -> Self {
+        Self {
+            status: ActionStatus::Ok,
+            retcode: 0,
+            data,
+            message: String::new(),
+            echo: None,
+        }
+    }
+
+    pub fn failed<S: Display>(retcode: i64, message: S) -> Self {
+        Self {
+            status: ActionStatus::Failed,
+            retcode,
+            data: serde_json::Value::Null,
+            message: message.to_string(),
+            echo: None,
+        }
+    }
+
+    pub(crate) fn to_json(&self) -> Result<String> {
+        let ret = serde_json::to_string(self)?;
+        Ok(ret)
     }
 }
 
 #[derive(Deserialize)]
 pub(crate) struct ActionJson {
     pub action: String,
+    #[serde(default)]
     pub params: serde_json::Value,
+    pub echo: Option<serde_json::Value>,
+}
+
+impl ActionJson {
+    fn from_bytes(bytes: &[u8]) -> std::result::Result<Self, (Error, Option<serde_json::Value>)> {
+        let text =
+            std::str::from_utf8(bytes).map_err(|err| (Error::BadRequest(err.to_string()), None))?;
+        let value = serde_json::from_str::<serde_json::Value>(text)
+            .map_err(|err| (Error::BadRequest(err.to_string()), None))?;
+        Self::from_value(value)
+    }
+
+    fn from_value(
+        mut value: serde_json::Value,
+    ) -> std::result::Result<Self, (Error, Option<serde_json::Value>)> {
+        let echo = value
+            .as_object_mut()
+            .and_then(|object| object.remove("echo"));
+        let mut action_json = serde_json::from_value::<Self>(value)
+            .map_err(|err| (Error::BadRequest(err.to_string()), echo.clone()))?;
+        action_json.echo = echo;
+        Ok(action_json)
+    }
+
+    async fn handle(self, action_handlers: &HashMap<String, Action>) -> Result<ActionResponse> {
+        let Self { action, params, .. } = self;
+        let params = match params {
+            serde_json::Value::Null => serde_json::Value::Object(serde_json::Map::new()),
+            params @ serde_json::Value::Object(_) => params,
+            _ => return Err(Error::BadParam("params 必须为对象".to_string())),
+        };
+        let handler = action_handlers
+            .get(&action)
+            .ok_or_else(|| Error::UnsupportedAction(action.clone()))?;
+        AssertUnwindSafe(handler.handle(params))
+            .catch_unwind()
+            .await
+            .map_err(|_| Error::InternalHandlerError(format!("{} 处理器 panic", action)))
+    }
+}
+
+pub(crate) async fn handle_action(
+    action_handlers: &HashMap<String, Action>,
+    bytes: &[u8],
+) -> ActionResponse {
+    respond(action_handlers, ActionJson::from_bytes(bytes)).await
+}
+
+pub(crate) async fn handle_action_value(
+    action_handlers: &HashMap<String, Action>,
+    value: serde_json::Value,
+) -> ActionResponse {
+    respond(action_handlers, ActionJson::from_value(value)).await
+}
+
+async fn respond(
+    action_handlers: &HashMap<String, Action>,
+    action_json: std::result::Result<ActionJson, (Error, Option<serde_json::Value>)>,
+) -> ActionResponse {
+    let (mut response, echo) = match action_json {
+        Ok(action_json) => {
+            let echo = action_json.echo.clone();
+            let response = action_json
+                .handle(action_handlers)
+                .await
+                .unwrap_or_else(ActionResponse::from);
+            (response, echo)
+        }
+        Err((err, echo)) => (ActionResponse::from(err), echo),
+    };
+    if response.status == ActionStatus::Failed {
+        log::warn!("动作请求失败：{}", response.message);
+    }
+    response.echo = echo;
+    response
 }
@@ -1,23 +1,33 @@
-use crate::{config::ConfigFileCommMethod, Action, Comm, Error, Event, Result};
+use crate::{
+    action::handle_action, comm::CommContext, config::ConfigFileCommMethod, Action, Comm, Error,
+    Result,
+};
 use async_trait::async_trait;
+use hyper::server::{accept::Accept, conn::AddrIncoming};
 use std::{
     collections::HashMap,
+    convert::Infallible,
     net::{SocketAddr, ToSocketAddrs},
+    pin::Pin,
+    sync::{Arc, Mutex},
 };
-use tokio::sync::broadcast::Sender;
-use warp::Filter;
+use warp::{Filter, Reply};
 
 #[derive(Debug, Clone)]
 pub struct HTTP {
     pub socket_addr: SocketAddr,
+    listener: Arc<Mutex<Option<std::net::TcpListener>>>,
 }
 
 impl HTTP {
     pub fn new<A: ToSocketAddrs>(socket_addr: A) -> Result<Self> {
         let mut addrs = socket_addr.to_socket_addrs()?;
         if let Some(addr) = addrs.next() {
-            if addrs.next() == None {
-                return Ok(Self { socket_addr: addr });
+            if addrs.next().is_none() {
+                return Ok(Self {
+                    socket_addr: addr,
+                    listener: Arc::default(),
+                });
             }
         };
         Err(Error::msg(format!(
@@ -26,6 +36,14 @@ impl HTTP {
         )))
     }
 
+    // 使用已绑定的监听套接字，例如绑定 0 端口后由系统分配的端口
+    pub fn from_listener(listener: std::net::TcpListener) -> Result<Self> {
+        Ok(Self {
+            socket_addr: listener.local_addr()?,
+            listener: Arc::new(Mutex::new(Some(listener))),
+        })
+    }
+
     pub(crate) fn from_config_file_comm_method(
         comm_method: &ConfigFileCommMethod,
     ) -> Result<Box<dyn Comm>> {
@@ -42,24 +60,66 @@ impl HTTP {
 
 #[async_trait]
 impl Comm for HTTP {
-    async fn start(
-        &self,
-        action_handlers: HashMap<String, Action>,
-        _event_sender: Sender<Event>,
-        _platform: String,
-    ) -> Result<()> {
+    async fn start(&self, context: CommContext) -> Result<()> {
+        let CommContext {
+            action_handlers,
+            access_token,
+            shutdown,
+            ..
+        } = context;
+        let action_handlers = Arc::new(action_handlers);
+        let access_token = Arc::new(access_token);
         let handler = warp::post()
+            .and(warp::header::optional::<String>("authorization"))
+            .and(warp::query::<HashMap<String, String>>())
             .and(warp::body::bytes())
-            .map(move |b: bytes::Bytes| {
-                let action_json = serde_json::from_str::<crate::action::ActionJson>(
-                    &std::str::from_utf8(&b).unwrap().to_owned(),
-                )
-                .unwrap();
-                let action = action_handlers.get(&action_json.action).unwrap();
-                (action.action)(action_json.params)
-            });
+            .and(warp::any().map(move || (action_handlers.clone(), access_token.clone())))
+            .and_then(
+                |authorization: Option<String>,
+                 query: HashMap<String, String>,
+                 b: bytes::Bytes,
+                 (action_handlers, access_token): (
+                    Arc<HashMap<String, Action>>,
+                    Arc<Option<String>>,
+                )| async move {
+                    if let Err(status) = super::verify_access_token(
+                        access_token.as_deref(),
+                        authorization.as_deref(),
+                        query.get("access_token").map(String::as_str),
+                    ) {
+                        return Ok::<_, Infallible>(
+                            warp::reply::with_status(warp::reply(), status).into_response(),
+                        );
+                    }
+                    let response = handle_action(&action_handlers, &b).await;
+                    Ok(warp::reply::json(&response).into_response())
+                },
+            );
 
-        warp::serve(handler).run(self.socket_addr).await;
+        let listener = super::bind_listener(self.socket_addr, &self.listener)
+            .await
+            .map_err(|err| Error::msg(format!("communication error: {}", err)))?;
+        // 与 warp 自行绑定时相同，由 hyper 接收连接，接收出错时等待后重试而不是结束服务器
+        let mut incoming = AddrIncoming::from_listener(listener)
+            .map_err(|err| Error::msg(format!("communication error: {}", err)))?;
+        incoming.set_nodelay(true);
+        let incoming = futures::stream::poll_fn(move |cx| Pin::new(&mut incoming).poll_accept(cx));
+        let graceful = shutdown.clone();
+        let server =
+            warp::serve(handler).serve_incoming_with_graceful_shutdown(incoming, async move {
+                graceful.cancelled().await
+            });
+        tokio::pin!(server);
+        tokio::select! {
+            _ = &mut server => return Ok(()),
+            _ = shutdown.cancelled() => {}
+        }
+        if tokio::time::timeout(super::SHUTDOWN_TIMEOUT, server)
+            .await
+            .is_err()
+        {
+            log::warn!("HTTP 服务器关闭超时，放弃等待剩余连接");
+        }
 
         Ok(())
     }
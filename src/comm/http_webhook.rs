@@ -1,12 +1,53 @@
-use crate::{config::ConfigFileCommMethod, Action, Comm, Event, Result};
+use crate::{
+    action::handle_action_value, comm::CommContext, config::ConfigFileCommMethod, Action, Comm,
+    Error, Result, SharedEvent,
+};
 use async_trait::async_trait;
-use std::{collections::HashMap, fmt::Display};
-use tokio::sync::broadcast::Sender;
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderValue, CONTENT_TYPE};
+use sha1::Sha1;
+use sha2::Sha256;
+use std::{collections::HashMap, fmt::Display, sync::Arc, time::Duration};
+use tokio::sync::Semaphore;
+use tokio_util::sync::CancellationToken;
+
+const MAX_RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signature {
+    Sha1,
+    Sha256,
+}
+
+impl Signature {
+    fn sign(&self, secret: &str, body: &[u8]) -> Result<String> {
+        let invalid_key = |_| Error::msg("无效的 HMAC 密钥");
+        Ok(match self {
+            Self::Sha1 => {
+                let mut mac =
+                    Hmac::<Sha1>::new_from_slice(secret.as_bytes()).map_err(invalid_key)?;
+                mac.update(body);
+                format!("sha1={}", hex::encode(mac.finalize().into_bytes()))
+            }
+            Self::Sha256 => {
+                let mut mac =
+                    Hmac::<Sha256>::new_from_slice(secret.as_bytes()).map_err(invalid_key)?;
+                mac.update(body);
+                format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+            }
+        })
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct HTTPWebHook {
     post_url: String,
     secret: Option<String>,
+    signature: Signature,
+    timeout: Duration,
+    max_retries: u32,
+    retry_interval: Duration,
+    max_concurrent_actions: usize,
 }
 
 impl HTTPWebHook {
@@ -14,14 +55,46 @@ impl HTTPWebHook {
         Self {
             post_url: post_url.to_string(),
             secret: None,
+            signature: Signature::Sha1,
+            timeout: Duration::from_secs(5),
+            max_retries: 3,
+            retry_interval: Duration::from_millis(500),
+            max_concurrent_actions: super::DEFAULT_MAX_CONCURRENT_ACTIONS,
         }
     }
 
+    // 单次请求的超时时间，为 0 时不限制
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    pub fn retry_interval(mut self, retry_interval: Duration) -> Self {
+        self.retry_interval = retry_interval;
+        self
+    }
+
+    // 同时执行的快速操作批次上限，达到上限时暂停投递后续事件
+    pub fn max_concurrent_actions(mut self, max_concurrent_actions: usize) -> Self {
+        self.max_concurrent_actions = max_concurrent_actions.max(1);
+        self
+    }
+
     pub fn secret<S: Display>(mut self, secret: S) -> Self {
         self.secret = Some(secret.to_string());
         self
     }
 
+    pub fn signature(mut self, signature: Signature) -> Self {
+        self.signature = signature;
+        self
+    }
+
     pub(crate) fn from_config_file_comm_method(
         comm_method: &ConfigFileCommMethod,
     ) -> Result<Box<dyn Comm>> {
@@ -34,30 +107,167 @@ impl HTTPWebHook {
         if let Some(secret) = comm_method.secret.clone() {
             http_webhook = http_webhook.secret(secret);
         }
+        if let Some(signature) = &comm_method.signature {
+            http_webhook = http_webhook.signature(if signature == "sha1" {
+                Signature::Sha1
+            } else if signature == "sha256" {
+                Signature::Sha256
+            } else {
+                return Err(Error::msg(
+                    "配置文件错误：未知的签名算法，应为：\"sha1\" 或 \"sha256\"",
+                ));
+            });
+        }
+        if let Some(timeout) = comm_method.timeout {
+            http_webhook = http_webhook.timeout(Duration::from_millis(timeout as u64));
+        }
+        if let Some(max_retries) = comm_method.max_retries {
+            http_webhook = http_webhook.max_retries(max_retries);
+        }
+        if let Some(retry_interval) = comm_method.retry_interval {
+            http_webhook =
+                http_webhook.retry_interval(Duration::from_millis(retry_interval as u64));
+        }
+        if let Some(max_concurrent_actions) = comm_method.max_concurrent_actions {
+            http_webhook = http_webhook.max_concurrent_actions(max_concurrent_actions);
+        }
         Ok(Box::new(http_webhook))
     }
+
+    async fn post_event(
+        &self,
+        client: &reqwest::Client,
+        event: &SharedEvent,
+        shutdown: &CancellationToken,
+    ) -> Result<bytes::Bytes> {
+        let body = event.json()?;
+        let signature = match &self.secret {
+            Some(secret) => Some(self.signature.sign(secret, &body)?),
+            None => None,
+        };
+
+        let mut retry_interval = self.retry_interval;
+        let mut retries = 0;
+        loop {
+            let mut request = client.post(&self.post_url).body(body.clone());
+            if !self.timeout.is_zero() {
+                request = request.timeout(self.timeout);
+            }
+            if let Some(signature) = &signature {
+                request = request.header("X-Signature", signature);
+            }
+            let (err, retryable) = match request.send().await {
+                Ok(response) if response.status().is_success() => {
+                    return Ok(response.bytes().await?)
+                }
+                Ok(response) => (
+                    Error::msg(format!("webhook 返回 HTTP 状态码 {}", response.status())),
+                    response.status().is_server_error(),
+                ),
+                Err(err) => (err.into(), true),
+            };
+            // 关闭时不再重试，避免拖慢退出
+            if !retryable || retries >= self.max_retries || shutdown.is_cancelled() {
+                return Err(err);
+            }
+            retries += 1;
+            log::warn!(
+                "事件投递失败：{}，{} 毫秒后进行第 {} 次重试",
+                err,
+                retry_interval.as_millis(),
+                retries
+            );
+            tokio::select! {
+                _ = shutdown.cancelled() => return Err(err),
+                _ = tokio::time::sleep(retry_interval) => {}
+            }
+            // 配置的重试间隔超过上限时不再增长，但也不会缩短
+            retry_interval = (retry_interval * 2).min(MAX_RETRY_INTERVAL.max(self.retry_interval));
+        }
+    }
+
+    async fn deliver(
+        &self,
+        client: &reqwest::Client,
+        event: SharedEvent,
+        shutdown: &CancellationToken,
+        quick_operations: &QuickOperations,
+    ) -> Result<()> {
+        let body = self.post_event(client, &event, shutdown).await?;
+
+        // 快速操作：响应体为动作请求列表；空响应体、JSON 对象或字符串视为普通的确认回复
+        let actions = match serde_json::from_slice::<serde_json::Value>(&body) {
+            Ok(serde_json::Value::Array(actions)) => actions,
+            Ok(serde_json::Value::Object(_)) | Ok(serde_json::Value::String(_)) => return Ok(()),
+            _ if body.iter().all(u8::is_ascii_whitespace) => return Ok(()),
+            _ => {
+                log::warn!("webhook 响应体不是动作请求列表，已忽略");
+                return Ok(());
+            }
+        };
+        quick_operations.spawn(actions).await;
+        Ok(())
+    }
+}
+
+// 快速操作在独立任务中依次执行，不阻塞后续事件的投递
+struct QuickOperations {
+    action_handlers: Arc<HashMap<String, Action>>,
+    semaphore: Arc<Semaphore>,
+}
+
+impl QuickOperations {
+    async fn spawn(&self, actions: Vec<serde_json::Value>) {
+        let permit = match self.semaphore.clone().acquire_owned().await {
+            Ok(permit) => permit,
+            Err(_) => return,
+        };
+        let action_handlers = self.action_handlers.clone();
+        tokio::spawn(async move {
+            for action in actions {
+                handle_action_value(&action_handlers, action).await;
+            }
+            drop(permit);
+        });
+    }
 }
 
 #[async_trait]
 impl Comm for HTTPWebHook {
-    async fn start(
-        &self,
-        _action_handlers: HashMap<String, Action>,
-        event_sender: Sender<Event>,
-        platform: String,
-    ) -> Result<()> {
-        let mut event_receiver = event_sender.subscribe();
-        let client = reqwest::Client::new();
+    async fn start(&self, context: CommContext) -> Result<()> {
+        let mut headers = context.header_map()?;
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        let client = reqwest::Client::builder()
+            .default_headers(headers)
+            .build()?;
+        let mut event_receiver = context.event_bus.subscribe();
+        let quick_operations = QuickOperations {
+            action_handlers: Arc::new(context.action_handlers.clone()),
+            semaphore: Arc::new(Semaphore::new(self.max_concurrent_actions)),
+        };
         loop {
-            let event = event_receiver.recv().await;
-            if let Ok(mut event) = event {
-                event = event.platform(&platform);
-                let _ = client
-                    .post(&self.post_url)
-                    .body(event.to_json().unwrap())
-                    .send()
-                    .await?;
+            let event = tokio::select! {
+                _ = context.shutdown.cancelled() => break,
+                event = event_receiver.recv() => event,
+            };
+            if let Err(err) = self
+                .deliver(&client, event, &context.shutdown, &quick_operations)
+                .await
+            {
+                log::error!("事件投递失败：{}", err);
             }
         }
+
+        // 关闭前投递已经收到的事件
+        while let Some(event) = event_receiver.try_recv() {
+            if let Err(err) = self
+                .deliver(&client, event, &context.shutdown, &quick_operations)
+                .await
+            {
+                log::error!("事件投递失败：{}", err);
+            }
+        }
+
+        Ok(())
     }
 }
@@ -1,7 +1,34 @@
-use crate::{config::ConfigFileCommMethod, Action, Error, Event, Result, Sender};
+use crate::{
+    action::handle_action, config::ConfigFileCommMethod, Action, Error, EventBus, EventReceiver,
+    Result, SharedEvent,
+};
 use async_trait::async_trait;
 use dyn_clonable::clonable;
-use std::{collections::HashMap, fmt::Debug};
+use futures_util::{Sink, SinkExt, StreamExt};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Debug,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use subtle::ConstantTimeEq;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpListener,
+    sync::{mpsc, mpsc::error::TrySendError, OwnedSemaphorePermit, Semaphore},
+    task::JoinHandle,
+    time::{Instant, Interval, MissedTickBehavior},
+};
+use tokio_tungstenite::{
+    tungstenite::{
+        protocol::{frame::coding::CloseCode, CloseFrame},
+        Message as TungsteniteMessage,
+    },
+    WebSocketStream,
+};
+use tokio_util::sync::CancellationToken;
+use warp::http::{HeaderMap, HeaderValue, StatusCode};
 
 mod http;
 mod http_webhook;
@@ -9,19 +36,468 @@ mod ws;
 mod ws_reverse;
 
 pub use http::HTTP;
-pub use http_webhook::HTTPWebHook;
+pub use http_webhook::{HTTPWebHook, Signature};
 pub use ws::WebSocket;
 pub use ws_reverse::WebSocketReverse;
 
+const WS_WRITER_CAPACITY: usize = 64;
+// 关闭时等待进行中的请求完成、关闭帧发出的最长时间，避免客户端拖住退出
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(3);
+const DEFAULT_MAX_CONCURRENT_ACTIONS: usize = 16;
+
+#[derive(Clone)]
+pub struct CommContext {
+    pub action_handlers: HashMap<String, Action>,
+    pub event_bus: EventBus,
+    pub platform: String,
+    pub self_id: String,
+    pub impl_name: String,
+    pub impl_version: String,
+    pub access_token: Option<String>,
+    pub shutdown: CancellationToken,
+}
+
+impl CommContext {
+    fn request_headers(&self) -> Vec<(&'static str, String)> {
+        let mut headers = vec![
+            (
+                "User-Agent",
+                format!(
+                    "OneBot/12 ({}) {}/{}",
+                    self.platform, self.impl_name, self.impl_version
+                ),
+            ),
+            ("X-OneBot-Version", "12".to_string()),
+            ("X-Impl", self.impl_name.clone()),
+            ("X-Platform", self.platform.clone()),
+            ("X-Self-ID", self.self_id.clone()),
+        ];
+        if let Some(access_token) = &self.access_token {
+            headers.push(("Authorization", format!("Bearer {}", access_token)));
+        }
+        headers
+    }
+
+    fn header_map(&self) -> Result<HeaderMap> {
+        let mut header_map = HeaderMap::new();
+        for (name, value) in self.request_headers() {
+            let value = HeaderValue::from_str(&value)
+                .map_err(|_| Error::msg(format!("无效的请求头 {}：{}", name, value)))?;
+            header_map.insert(name, value);
+        }
+        Ok(header_map)
+    }
+}
+
 #[async_trait]
 #[clonable]
 pub trait Comm: Clone + Debug + Send + Sync {
-    async fn start(
+    async fn start(&self, context: CommContext) -> Result<()>;
+}
+
+// 预先绑定的监听套接字只在首次启动时使用，随通信方式的任务结束而关闭；
+// 之后的启动重新绑定 socket_addr
+async fn bind_listener(
+    socket_addr: SocketAddr,
+    listener: &Mutex<Option<std::net::TcpListener>>,
+) -> Result<TcpListener> {
+    let listener = listener.lock().unwrap().take();
+    match listener {
+        Some(listener) => {
+            listener.set_nonblocking(true)?;
+            Ok(TcpListener::from_std(listener)?)
+        }
+        None => Ok(TcpListener::bind(socket_addr).await?),
+    }
+}
+
+fn verify_access_token(
+    access_token: Option<&str>,
+    authorization: Option<&str>,
+    query_access_token: Option<&str>,
+) -> std::result::Result<(), StatusCode> {
+    let access_token = match access_token {
+        Some(access_token) => access_token,
+        None => return Ok(()),
+    };
+    let provided = authorization.and_then(bearer_token).or(query_access_token);
+    match provided {
+        Some(provided) if bool::from(provided.as_bytes().ct_eq(access_token.as_bytes())) => Ok(()),
+        Some(_) => Err(StatusCode::FORBIDDEN),
+        None => Err(StatusCode::UNAUTHORIZED),
+    }
+}
+
+// 认证方案名称不区分大小写
+fn bearer_token(authorization: &str) -> Option<&str> {
+    let (scheme, token) = authorization.split_once(' ')?;
+    if scheme.eq_ignore_ascii_case("Bearer") {
+        Some(token.trim_start())
+    } else {
+        None
+    }
+}
+
+fn query_access_token(query: Option<&str>) -> Option<String> {
+    serde_urlencoded::from_str::<HashMap<String, String>>(query?)
+        .ok()?
+        .remove("access_token")
+}
+
+async fn handle_ws_action(
+    action_handlers: &HashMap<String, Action>,
+    msg: TungsteniteMessage,
+) -> Option<TungsteniteMessage> {
+    let response = match msg {
+        TungsteniteMessage::Text(text) => handle_action(action_handlers, text.as_bytes()).await,
+        TungsteniteMessage::Binary(bytes) => handle_action(action_handlers, &bytes).await,
+        _ => return None,
+    };
+    match response.to_json() {
+        Ok(json) => Some(TungsteniteMessage::Text(json)),
+        Err(err) => {
+            log::warn!("动作响应序列化失败：{}", err);
+            None
+        }
+    }
+}
+
+fn event_message(event: &SharedEvent) -> Option<TungsteniteMessage> {
+    match event.json_string() {
+        Ok(json) => Some(TungsteniteMessage::Text(json)),
+        Err(err) => {
+            log::warn!("事件序列化失败：{}", err);
+            None
+        }
+    }
+}
+
+// 交给写入任务的消息；事件在写入任务中序列化，未能写入时原样交还
+enum WsOutgoing {
+    Event(SharedEvent),
+    Message(TungsteniteMessage),
+}
+
+// 同一连接上的事件与动作响应统一交由写入任务发送，发送关闭帧或 stop 取消后写入任务退出，
+// 并返回尚未写入的事件；写入中途被打断的事件可能已经送达，重连后会再次发送
+fn spawn_ws_writer<S>(
+    mut ws_sender: S,
+    stop: CancellationToken,
+) -> (mpsc::Sender<WsOutgoing>, JoinHandle<Vec<SharedEvent>>)
+where
+    S: Sink<TungsteniteMessage> + Unpin + Send + 'static,
+{
+    let (writer, mut messages) = mpsc::channel(WS_WRITER_CAPACITY);
+    let task = tokio::spawn(async move {
+        let mut undelivered = Vec::new();
+        loop {
+            let outgoing = tokio::select! {
+                _ = stop.cancelled() => break,
+                outgoing = messages.recv() => match outgoing {
+                    Some(outgoing) => outgoing,
+                    None => break,
+                },
+            };
+            let (msg, event) = match outgoing {
+                WsOutgoing::Event(event) => match event_message(&event) {
+                    Some(msg) => (msg, Some(event)),
+                    None => continue,
+                },
+                WsOutgoing::Message(msg) => (msg, None),
+            };
+            let close = matches!(msg, TungsteniteMessage::Close(_));
+            let sent = tokio::select! {
+                _ = stop.cancelled() => false,
+                sent = ws_sender.send(msg) => sent.is_ok(),
+            };
+            if !sent {
+                undelivered.extend(event);
+                break;
+            }
+            if close {
+                break;
+            }
+        }
+        messages.close();
+        while let Ok(outgoing) = messages.try_recv() {
+            if let WsOutgoing::Event(event) = outgoing {
+                undelivered.push(event);
+            }
+        }
+        undelivered
+    });
+    (writer, task)
+}
+
+// 在独立任务中执行动作，permit 在动作完成后释放；响应依靠 echo 与请求对应
+fn spawn_ws_action(
+    action_handlers: Arc<HashMap<String, Action>>,
+    msg: TungsteniteMessage,
+    permit: OwnedSemaphorePermit,
+    writer: mpsc::Sender<WsOutgoing>,
+) {
+    tokio::spawn(async move {
+        if let Some(response) = handle_ws_action(&action_handlers, msg).await {
+            let _ = writer.send(WsOutgoing::Message(response)).await;
+        }
+        drop(permit);
+    });
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Keepalive {
+    ping_interval: Duration,
+    pong_timeout: Duration,
+}
+
+impl Keepalive {
+    fn new(ping_interval: Duration, pong_timeout: Duration) -> Self {
+        Self {
+            ping_interval,
+            pong_timeout,
+        }
+    }
+
+    fn default_enabled() -> Option<Self> {
+        Some(Self::new(Duration::from_secs(30), Duration::from_secs(10)))
+    }
+
+    // ping_interval 为 0 时关闭保活
+    fn from_config_file_comm_method(comm_method: &ConfigFileCommMethod) -> Option<Self> {
+        let default = Self::default_enabled()?;
+        match comm_method.ping_interval {
+            Some(0) => None,
+            ping_interval => Some(Self::new(
+                ping_interval
+                    .map(|ms| Duration::from_millis(ms as u64))
+                    .unwrap_or(default.ping_interval),
+                comm_method
+                    .pong_timeout
+                    .map(|ms| Duration::from_millis(ms as u64))
+                    .unwrap_or(default.pong_timeout),
+            )),
+        }
+    }
+}
+
+enum KeepaliveTick {
+    Ping,
+    Timeout,
+}
+
+// 单个连接的保活计时：定期发送 Ping，收到任何帧即视为连接存活
+struct KeepaliveTimer {
+    keepalive: Option<Keepalive>,
+    ping: Option<Interval>,
+    pong_deadline: Option<Instant>,
+}
+
+impl KeepaliveTimer {
+    fn new(keepalive: Option<Keepalive>) -> Self {
+        let ping = keepalive.map(|keepalive| {
+            let mut ping = tokio::time::interval_at(
+                Instant::now() + keepalive.ping_interval,
+                keepalive.ping_interval,
+            );
+            ping.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            ping
+        });
+        Self {
+            keepalive,
+            ping,
+            pong_deadline: None,
+        }
+    }
+
+    fn alive(&mut self) {
+        self.pong_deadline = None;
+    }
+
+    async fn tick(&mut self) -> KeepaliveTick {
+        let (keepalive, ping) = match (self.keepalive, &mut self.ping) {
+            (Some(keepalive), Some(ping)) => (keepalive, ping),
+            _ => return futures::future::pending().await,
+        };
+        match self.pong_deadline {
+            Some(deadline) => {
+                tokio::time::sleep_until(deadline).await;
+                KeepaliveTick::Timeout
+            }
+            None => {
+                ping.tick().await;
+                self.pong_deadline = Some(Instant::now() + keepalive.pong_timeout);
+                KeepaliveTick::Ping
+            }
+        }
+    }
+}
+
+fn close_message() -> TungsteniteMessage {
+    TungsteniteMessage::Close(Some(CloseFrame {
+        code: CloseCode::Away,
+        reason: "OneBot shutdown".into(),
+    }))
+}
+
+// 未能送达的事件，超出容量时丢弃最早的事件；容量为 0 时不缓冲
+struct EventBuffer {
+    events: VecDeque<SharedEvent>,
+    capacity: usize,
+}
+
+impl EventBuffer {
+    fn new(capacity: usize) -> Self {
+        Self {
+            events: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    fn push(&mut self, event: SharedEvent) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.events.len() >= self.capacity {
+            self.events.pop_front();
+            log::warn!("WebSocket 事件缓冲区已满，丢弃最早的事件");
+        }
+        self.events.push_back(event);
+    }
+
+    // 把写入任务交还的事件按原顺序放回缓冲区头部
+    fn requeue(&mut self, events: Vec<SharedEvent>) {
+        for event in events.into_iter().rev() {
+            self.events.push_front(event);
+        }
+        if self.events.len() > self.capacity {
+            let overflow = self.events.len() - self.capacity;
+            self.events.drain(..overflow);
+            if self.capacity > 0 {
+                log::warn!("WebSocket 事件缓冲区已满，丢弃最早的 {} 个事件", overflow);
+            }
+        }
+    }
+}
+
+async fn next_event(event_receiver: &mut Option<EventReceiver>) -> SharedEvent {
+    match event_receiver {
+        Some(event_receiver) => event_receiver.recv().await,
+        None => futures::future::pending().await,
+    }
+}
+
+// 正向与反向 WebSocket 共用的单个连接的收发循环
+#[derive(Clone)]
+struct WsConnection {
+    action_handlers: Arc<HashMap<String, Action>>,
+    keepalive: Option<Keepalive>,
+    max_concurrent_actions: usize,
+    // 仅传输事件的连接忽略对端发来的动作请求
+    handle_actions: bool,
+    shutdown: CancellationToken,
+}
+
+impl WsConnection {
+    // 先发送缓冲区中的事件；返回 Ok 表示收到关闭信号，返回 Err 表示连接断开，
+    // 尚未写入的事件放回缓冲区
+    async fn serve<S>(
         &self,
-        action_handlers: HashMap<String, Action>,
-        event_receiver: Sender<Event>,
-        platform: String,
-    ) -> Result<()>;
+        ws_stream: WebSocketStream<S>,
+        event_receiver: &mut Option<EventReceiver>,
+        buffer: &mut EventBuffer,
+    ) -> Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let (ws_sender, mut ws_receiver) = ws_stream.split();
+        let stop = CancellationToken::new();
+        let (writer, mut writer_task) = spawn_ws_writer(ws_sender, stop.clone());
+        let result: Result<()> = async {
+            let semaphore = Arc::new(Semaphore::new(self.max_concurrent_actions));
+            let mut permit = None;
+            let mut keepalive = KeepaliveTimer::new(self.keepalive);
+            // 写入队列有空位时才取出事件，对端停止读取也不会阻塞收发循环
+            let mut slot = None;
+
+            loop {
+                tokio::select! {
+                    _ = self.shutdown.cancelled() => return Ok(()),
+                    tick = keepalive.tick() => match tick {
+                        // 写入队列已满时跳过本次 Ping，对端一直不读取会导致 Pong 超时
+                        KeepaliveTick::Ping => {
+                            let ping = WsOutgoing::Message(TungsteniteMessage::Ping(Vec::new()));
+                            if let Err(TrySendError::Closed(_)) = writer.try_send(ping) {
+                                return Err(Error::msg("连接已断开"));
+                            }
+                        }
+                        // 动作并发已满时暂停读取，此时收不到 Pong，不视为超时
+                        KeepaliveTick::Timeout if permit.is_none() => keepalive.alive(),
+                        KeepaliveTick::Timeout => return Err(Error::msg("Pong 超时")),
+                    },
+                    reserved = writer.reserve(), if slot.is_none() => match reserved {
+                        Ok(reserved) => slot = Some(reserved),
+                        Err(_) => return Err(Error::msg("连接已断开")),
+                    },
+                    event = async {
+                        match buffer.events.pop_front() {
+                            Some(event) => event,
+                            None => next_event(event_receiver).await,
+                        }
+                    }, if slot.is_some() => {
+                        if let Some(slot) = slot.take() {
+                            slot.send(WsOutgoing::Event(event));
+                        }
+                    }
+                    acquired = semaphore.clone().acquire_owned(), if permit.is_none() => {
+                        permit = acquired.ok();
+                    }
+                    // Ping 与关闭帧的回复由 tungstenite 在下一次读取时自动发出
+                    msg = ws_receiver.next(), if permit.is_some() => match msg {
+                        Some(Ok(msg)) => {
+                            keepalive.alive();
+                            if !self.handle_actions || !(msg.is_text() || msg.is_binary()) {
+                                continue;
+                            }
+                            if let Some(permit) = permit.take() {
+                                spawn_ws_action(
+                                    self.action_handlers.clone(),
+                                    msg,
+                                    permit,
+                                    writer.clone(),
+                                );
+                            }
+                        }
+                        Some(Err(err)) => return Err(err.into()),
+                        None => return Err(Error::msg("连接已被对端关闭")),
+                    },
+                }
+            }
+        }
+        .await;
+        if self.shutdown.is_cancelled() {
+            // 对端停止读取时写入任务会一直阻塞，超时后直接断开
+            let close = async {
+                if writer
+                    .send(WsOutgoing::Message(close_message()))
+                    .await
+                    .is_ok()
+                {
+                    drop(writer);
+                    let _ = (&mut writer_task).await;
+                }
+            };
+            if tokio::time::timeout(SHUTDOWN_TIMEOUT, close).await.is_err() {
+                log::warn!("WebSocket 关闭帧发送超时，强制断开连接");
+            }
+            writer_task.abort();
+        } else {
+            stop.cancel();
+            if let Ok(undelivered) = writer_task.await {
+                buffer.requeue(undelivered);
+            }
+        }
+        result
+    }
 }
 
 pub(crate) fn from_config_file_comm_method(
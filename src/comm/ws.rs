@@ -1,24 +1,36 @@
-use crate::{config::ConfigFileCommMethod, Action, Comm, Error, Event, Result};
+use crate::{
+    comm::{CommContext, EventBuffer, Keepalive, WsConnection},
+    config::ConfigFileCommMethod,
+    Comm, Error, Result,
+};
 use async_trait::async_trait;
-use futures_util::{SinkExt, StreamExt};
 use std::{
-    collections::HashMap,
     net::{SocketAddr, ToSocketAddrs},
+    sync::{Arc, Mutex},
+    time::Duration,
 };
-use tokio::{net::TcpListener, sync::broadcast::Sender};
-use tokio_tungstenite::tungstenite::Message as TungsteniteMessage;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
 
 #[derive(Debug, Clone)]
 pub struct WebSocket {
     socket_addr: SocketAddr,
+    listener: Arc<Mutex<Option<std::net::TcpListener>>>,
+    keepalive: Option<Keepalive>,
+    max_concurrent_actions: usize,
 }
 
 impl WebSocket {
     pub fn new<A: ToSocketAddrs>(socket_addr: A) -> Result<Self> {
         let mut addrs = socket_addr.to_socket_addrs()?;
         if let Some(addr) = addrs.next() {
-            if addrs.next() == None {
-                return Ok(Self { socket_addr: addr });
+            if addrs.next().is_none() {
+                return Ok(Self {
+                    socket_addr: addr,
+                    listener: Arc::default(),
+                    keepalive: Keepalive::default_enabled(),
+                    max_concurrent_actions: super::DEFAULT_MAX_CONCURRENT_ACTIONS,
+                });
             }
         };
         Err(Error::msg(format!(
@@ -27,75 +39,124 @@ impl WebSocket {
         )))
     }
 
+    // 使用已绑定的监听套接字，例如绑定 0 端口后由系统分配的端口
+    pub fn from_listener(listener: std::net::TcpListener) -> Result<Self> {
+        Ok(Self {
+            socket_addr: listener.local_addr()?,
+            listener: Arc::new(Mutex::new(Some(listener))),
+            keepalive: Keepalive::default_enabled(),
+            max_concurrent_actions: super::DEFAULT_MAX_CONCURRENT_ACTIONS,
+        })
+    }
+
+    pub fn keepalive(mut self, ping_interval: Duration, pong_timeout: Duration) -> Self {
+        self.keepalive = Some(Keepalive::new(ping_interval, pong_timeout));
+        self
+    }
+
+    pub fn disable_keepalive(mut self) -> Self {
+        self.keepalive = None;
+        self
+    }
+
+    pub fn max_concurrent_actions(mut self, max_concurrent_actions: usize) -> Self {
+        self.max_concurrent_actions = max_concurrent_actions.max(1);
+        self
+    }
+
     pub(crate) fn from_config_file_comm_method(
         comm_method: &ConfigFileCommMethod,
     ) -> Result<Box<dyn Comm>> {
-        Ok(Box::new(Self::new(format!(
+        let mut ws = Self::new(format!(
             "{}:{}",
             comm_method
                 .host
                 .clone()
                 .unwrap_or_else(|| "127.0.0.1".to_string()),
             comm_method.port.unwrap_or(6700)
-        ))?))
+        ))?;
+        ws.keepalive = Keepalive::from_config_file_comm_method(comm_method);
+        if let Some(max_concurrent_actions) = comm_method.max_concurrent_actions {
+            ws = ws.max_concurrent_actions(max_concurrent_actions);
+        }
+        Ok(Box::new(ws))
     }
 }
 
 #[async_trait]
 impl Comm for WebSocket {
-    async fn start(
-        &self,
-        action_handlers: HashMap<String, Action>,
-        event_sender: Sender<Event>,
-        platform: String,
-    ) -> Result<()> {
+    async fn start(&self, context: CommContext) -> Result<()> {
+        let CommContext {
+            action_handlers,
+            event_bus,
+            access_token,
+            shutdown,
+            ..
+        } = context;
         let socket_addr = self.socket_addr;
+        let connection = WsConnection {
+            action_handlers: Arc::new(action_handlers),
+            keepalive: self.keepalive,
+            max_concurrent_actions: self.max_concurrent_actions,
+            handle_actions: true,
+            shutdown: shutdown.clone(),
+        };
 
-        let listener = TcpListener::bind(&socket_addr).await?;
+        let listener = super::bind_listener(socket_addr, &self.listener).await?;
+        let mut connections: Vec<JoinHandle<()>> = Vec::new();
 
-        while let Ok((stream, _)) = listener.accept().await {
-            let mut event_receiver = event_sender.subscribe();
-            let platform = platform.clone();
-            let action_handlers = action_handlers.clone();
-            tokio::spawn(async move {
-                let ws_stream = tokio_tungstenite::accept_async(stream).await.unwrap();
-                let (mut ws_sender, mut ws_receiver) = ws_stream.split();
+        loop {
+            let stream = tokio::select! {
+                _ = shutdown.cancelled() => break,
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => stream,
+                    Err(_) => break,
+                },
+            };
+            connections.retain(|connection| !connection.is_finished());
 
-                loop {
-                    tokio::select! {
-                        event = event_receiver.recv() => {
-                            if let Ok(mut event) = event {
-                                event = event.platform(&platform);
-                                ws_sender
-                                    .send(TungsteniteMessage::Text(event.to_json().unwrap()))
-                                    .await
-                                    .unwrap();
-                            }
-                        }
-                        msg = ws_receiver.next() => {
-                            if let Some(Ok(msg)) = msg {
-                                if let Ok(text) = msg.into_text() {
-                                    let action_json =
-                                        serde_json::from_str::<crate::action::ActionJson>(
-                                            &text,
-                                        )
-                                        .unwrap();
-                                    let action =
-                                        action_handlers.get(&action_json.action).unwrap();
-                                    ws_sender
-                                        .send(TungsteniteMessage::Text((action.action)(
-                                            action_json.params,
-                                        )))
-                                        .await
-                                        .unwrap();
-                                }
-                            }
+            let mut event_receiver = Some(event_bus.subscribe());
+            let connection = connection.clone();
+            let access_token = access_token.clone();
+            connections.push(tokio::spawn(async move {
+                #[allow(clippy::result_large_err)]
+                let callback = |request: &Request, response: Response| {
+                    let authorization = request
+                        .headers()
+                        .get("authorization")
+                        .and_then(|value| value.to_str().ok());
+                    let query_access_token = super::query_access_token(request.uri().query());
+                    match super::verify_access_token(
+                        access_token.as_deref(),
+                        authorization,
+                        query_access_token.as_deref(),
+                    ) {
+                        Ok(()) => Ok(response),
+                        Err(status) => {
+                            let mut response = ErrorResponse::new(None);
+                            *response.status_mut() = status;
+                            Err(response)
                         }
                     }
+                };
+                let ws_stream = match tokio_tungstenite::accept_hdr_async(stream, callback).await {
+                    Ok(ws_stream) => ws_stream,
+                    Err(err) => {
+                        log::warn!("WebSocket 握手失败：{}", err);
+                        return;
+                    }
+                };
+                if let Err(err) = connection
+                    .serve(ws_stream, &mut event_receiver, &mut EventBuffer::new(0))
+                    .await
+                {
+                    log::info!("WebSocket 连接断开：{}", err);
                 }
-            });
+            }));
         }
 
+        futures::future::join_all(connections).await;
+
         Ok(())
     }
 }
@@ -1,79 +1,209 @@
-use crate::{config::ConfigFileCommMethod, Action, Comm, Event, Result};
+use crate::{
+    comm::{next_event, CommContext, EventBuffer, Keepalive, WsConnection},
+    config::ConfigFileCommMethod,
+    Comm, Error, Result,
+};
 use async_trait::async_trait;
-use futures_util::{SinkExt, StreamExt};
-use std::{collections::HashMap, fmt::Display};
-use tokio::sync::broadcast::Sender;
-use tokio_tungstenite::tungstenite::Message as TungsteniteMessage;
+use rand::Rng;
+use std::{fmt::Display, sync::Arc, time::Duration};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+#[derive(Debug, Clone)]
+enum Endpoint {
+    Universal(String),
+    Split { event_url: String, api_url: String },
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Role {
+    Universal,
+    Event,
+    Api,
+}
 
 #[derive(Debug, Clone)]
 pub struct WebSocketReverse {
-    connect_url: String,
+    endpoint: Endpoint,
+    reconnect_interval: Duration,
+    max_reconnect_interval: Option<Duration>,
+    buffer_size: usize,
+    keepalive: Option<Keepalive>,
+    max_concurrent_actions: usize,
 }
 
 impl WebSocketReverse {
     pub fn new<S: Display>(connect_url: S) -> Self {
+        Self::with_endpoint(Endpoint::Universal(connect_url.to_string()))
+    }
+
+    // 事件与动作分别通过 event_url 和 api_url 两条连接传输
+    pub fn split<E: Display, A: Display>(event_url: E, api_url: A) -> Self {
+        Self::with_endpoint(Endpoint::Split {
+            event_url: event_url.to_string(),
+            api_url: api_url.to_string(),
+        })
+    }
+
+    fn with_endpoint(endpoint: Endpoint) -> Self {
         Self {
-            connect_url: connect_url.to_string(),
+            endpoint,
+            reconnect_interval: Duration::from_secs(3),
+            max_reconnect_interval: None,
+            buffer_size: 100,
+            keepalive: Keepalive::default_enabled(),
+            max_concurrent_actions: super::DEFAULT_MAX_CONCURRENT_ACTIONS,
         }
     }
 
+    pub fn reconnect_interval(mut self, reconnect_interval: Duration) -> Self {
+        self.reconnect_interval = reconnect_interval;
+        self
+    }
+
+    // 启用带随机抖动的指数退避，重连间隔不超过 max_reconnect_interval
+    pub fn backoff(mut self, max_reconnect_interval: Duration) -> Self {
+        self.max_reconnect_interval = Some(max_reconnect_interval);
+        self
+    }
+
+    pub fn buffer_size(mut self, buffer_size: usize) -> Self {
+        self.buffer_size = buffer_size;
+        self
+    }
+
+    pub fn keepalive(mut self, ping_interval: Duration, pong_timeout: Duration) -> Self {
+        self.keepalive = Some(Keepalive::new(ping_interval, pong_timeout));
+        self
+    }
+
+    pub fn disable_keepalive(mut self) -> Self {
+        self.keepalive = None;
+        self
+    }
+
+    pub fn max_concurrent_actions(mut self, max_concurrent_actions: usize) -> Self {
+        self.max_concurrent_actions = max_concurrent_actions.max(1);
+        self
+    }
+
     pub(crate) fn from_config_file_comm_method(
         comm_method: &ConfigFileCommMethod,
     ) -> Result<Box<dyn Comm>> {
-        let ws_reverse = Self::new(
-            comm_method
-                .url
-                .clone()
-                .unwrap_or_else(|| "127.0.0.1:5700".to_string()),
-        );
+        let mut ws_reverse = match (&comm_method.event_url, &comm_method.api_url) {
+            (Some(event_url), Some(api_url)) => Self::split(event_url, api_url),
+            (None, None) => Self::new(
+                comm_method
+                    .url
+                    .clone()
+                    .unwrap_or_else(|| "127.0.0.1:5700".to_string()),
+            ),
+            _ => {
+                return Err(Error::msg(
+                    "配置文件错误：event_url 与 api_url 必须同时提供",
+                ))
+            }
+        };
+        if let Some(reconnect_interval) = comm_method.reconnect_interval {
+            ws_reverse =
+                ws_reverse.reconnect_interval(Duration::from_millis(reconnect_interval as u64));
+        }
+        if let Some(max_reconnect_interval) = comm_method.max_reconnect_interval {
+            ws_reverse = ws_reverse.backoff(Duration::from_millis(max_reconnect_interval as u64));
+        }
+        if let Some(buffer_size) = comm_method.buffer_size {
+            ws_reverse = ws_reverse.buffer_size(buffer_size);
+        }
+        ws_reverse.keepalive = Keepalive::from_config_file_comm_method(comm_method);
+        if let Some(max_concurrent_actions) = comm_method.max_concurrent_actions {
+            ws_reverse = ws_reverse.max_concurrent_actions(max_concurrent_actions);
+        }
         Ok(Box::new(ws_reverse))
     }
-}
 
-#[async_trait]
-impl Comm for WebSocketReverse {
-    async fn start(
-        &self,
-        action_handlers: HashMap<String, Action>,
-        event_sender: Sender<Event>,
-        platform: String,
-    ) -> Result<()> {
-        let mut event_receiver = event_sender.subscribe();
+    fn reconnect_delay(&self, failures: u32) -> Duration {
+        match self.max_reconnect_interval {
+            Some(max_reconnect_interval) => {
+                let delay = self
+                    .reconnect_interval
+                    .saturating_mul(2u32.saturating_pow(failures.saturating_sub(1)))
+                    .min(max_reconnect_interval);
+                delay.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+            }
+            None => self.reconnect_interval,
+        }
+    }
 
-        let (ws_stream, _) = tokio_tungstenite::connect_async(&self.connect_url).await?;
-        let (mut ws_sender, mut ws_receiver) = ws_stream.split();
+    async fn run(&self, url: &str, role: Role, context: &CommContext) -> Result<()> {
+        let headers = context.header_map()?;
+        let mut event_receiver = match role {
+            Role::Universal | Role::Event => Some(context.event_bus.subscribe()),
+            Role::Api => None,
+        };
+        let connection = WsConnection {
+            action_handlers: Arc::new(context.action_handlers.clone()),
+            keepalive: self.keepalive,
+            max_concurrent_actions: self.max_concurrent_actions,
+            handle_actions: !matches!(role, Role::Event),
+            shutdown: context.shutdown.clone(),
+        };
+        let mut buffer = EventBuffer::new(self.buffer_size);
+        let mut failures = 0;
 
         loop {
-            tokio::select! {
-                event = event_receiver.recv() => {
-                    if let Ok(mut event) = event {
-                        event = event.platform(&platform);
-                        ws_sender
-                            .send(TungsteniteMessage::Text(event.to_json().unwrap()))
-                            .await
-                            .unwrap();
+            log::info!(
+                "正在连接反向 WebSocket {}（第 {} 次尝试）",
+                url,
+                failures + 1
+            );
+            let mut request = url.into_client_request()?;
+            request.headers_mut().extend(headers.clone());
+            let connected = tokio::select! {
+                _ = context.shutdown.cancelled() => return Ok(()),
+                connected = tokio_tungstenite::connect_async(request) => connected,
+            };
+            match connected {
+                Ok((ws_stream, _)) => {
+                    log::info!("反向 WebSocket {} 已连接", url);
+                    failures = 0;
+                    match connection
+                        .serve(ws_stream, &mut event_receiver, &mut buffer)
+                        .await
+                    {
+                        Ok(()) => return Ok(()),
+                        Err(err) => log::warn!("反向 WebSocket {} 断开：{}", url, err),
                     }
                 }
-                msg = ws_receiver.next() => {
-                    if let Some(Ok(msg)) = msg {
-                        if let Ok(text) = msg.into_text() {
-                            let action_json =
-                                serde_json::from_str::<crate::action::ActionJson>(
-                                    &text,
-                                )
-                                .unwrap();
-                            let action =
-                                action_handlers.get(&action_json.action).unwrap();
-                            ws_sender
-                                .send(TungsteniteMessage::Text((action.action)(
-                                    action_json.params,
-                                )))
-                                .await
-                                .unwrap();
-                        }
-                    }
+                Err(err) => log::warn!("反向 WebSocket {} 连接失败：{}", url, err),
+            }
+
+            failures += 1;
+            let delay = self.reconnect_delay(failures);
+            log::info!("{} 毫秒后重连反向 WebSocket {}", delay.as_millis(), url);
+            let reconnect = tokio::time::sleep(delay);
+            tokio::pin!(reconnect);
+            loop {
+                tokio::select! {
+                    _ = context.shutdown.cancelled() => return Ok(()),
+                    _ = &mut reconnect => break,
+                    event = next_event(&mut event_receiver) => buffer.push(event),
                 }
             }
         }
     }
 }
+
+#[async_trait]
+impl Comm for WebSocketReverse {
+    async fn start(&self, context: CommContext) -> Result<()> {
+        match &self.endpoint {
+            Endpoint::Universal(url) => self.run(url, Role::Universal, &context).await,
+            Endpoint::Split { event_url, api_url } => {
+                let (event_result, api_result) = tokio::join!(
+                    self.run(event_url, Role::Event, &context),
+                    self.run(api_url, Role::Api, &context)
+                );
+                event_result.and(api_result)
+            }
+        }
+    }
+}
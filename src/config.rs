@@ -7,6 +7,7 @@ pub struct Config {
     pub auth: Auth,
     pub heartbeat: Option<u32>,
     pub log: Log,
+    pub event_queue: EventQueue,
 }
 
 impl Default for Config {
@@ -25,6 +26,7 @@ impl Config {
                 path: Some("./onebot.log".to_string()),
                 level: log::LevelFilter::Info,
             },
+            event_queue: EventQueue::new(1024, OverflowPolicy::DropOldest),
         }
     }
 
@@ -93,6 +95,12 @@ impl Config {
             }
         }
 
+        if let Some(event_queue) = config_file.event_queue() {
+            config.event_queue = config
+                .event_queue
+                .with_overrides(event_queue.capacity, event_queue.overflow.as_deref())?;
+        }
+
         Ok(config)
     }
 }
@@ -117,11 +125,57 @@ pub enum LogOutput {
     Nul,
 }
 
+// 事件队列已满时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    DropOldest,
+    DropNewest,
+    // 等待队列腾出空间，事件生成器随之阻塞
+    Block,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventQueue {
+    pub capacity: usize,
+    pub overflow: OverflowPolicy,
+}
+
+impl EventQueue {
+    pub fn new(capacity: usize, overflow: OverflowPolicy) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            overflow,
+        }
+    }
+
+    pub(crate) fn with_overrides(
+        self,
+        capacity: Option<usize>,
+        overflow: Option<&str>,
+    ) -> Result<Self> {
+        let overflow = match overflow {
+            None => self.overflow,
+            Some("drop_oldest") => OverflowPolicy::DropOldest,
+            Some("drop_newest") => OverflowPolicy::DropNewest,
+            Some("block") => OverflowPolicy::Block,
+            Some(_) => {
+                return Err(Error::msg(
+                    "配置文件错误：未知的事件队列溢出策略，应为：\"drop_oldest\"、\"drop_newest\" 或 \"block\"",
+                ))
+            }
+        };
+        Ok(Self::new(capacity.unwrap_or(self.capacity), overflow))
+    }
+}
+
 pub trait ConfigFile: Debug {
     fn auth(&self) -> Option<&ConfigFileAuth>;
     fn comm_methods(&self) -> Option<&HashMap<String, ConfigFileCommMethod>>;
     fn heartbeat(&self) -> Option<&ConfigFileHeartBeat>;
     fn log(&self) -> Option<&ConfigFileLog>;
+    fn event_queue(&self) -> Option<&ConfigFileEventQueue> {
+        None
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -137,11 +191,24 @@ pub struct ConfigFileCommMethod {
     pub port: Option<u16>,
 
     pub url: Option<String>,
+    pub event_url: Option<String>,
+    pub api_url: Option<String>,
 
     pub timeout: Option<u32>,
+    pub max_retries: Option<u32>,
+    pub retry_interval: Option<u32>,
     pub secret: Option<String>,
+    pub signature: Option<String>,
 
     pub reconnect_interval: Option<u32>,
+    pub ping_interval: Option<u32>,
+    pub max_concurrent_actions: Option<usize>,
+    pub pong_timeout: Option<u32>,
+    pub max_reconnect_interval: Option<u32>,
+    pub buffer_size: Option<usize>,
+
+    pub queue_capacity: Option<usize>,
+    pub queue_overflow: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -158,12 +225,19 @@ pub struct ConfigFileLog {
     pub level: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ConfigFileEventQueue {
+    pub capacity: Option<usize>,
+    pub overflow: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct DefaultConfigFile {
     auth: Option<ConfigFileAuth>,
     comm_method: Option<HashMap<String, ConfigFileCommMethod>>,
     heartbeat: Option<ConfigFileHeartBeat>,
     log: Option<ConfigFileLog>,
+    event_queue: Option<ConfigFileEventQueue>,
 }
 
 impl DefaultConfigFile {
@@ -173,6 +247,7 @@ impl DefaultConfigFile {
             comm_method: None,
             heartbeat: None,
             log: None,
+            event_queue: None,
         }
     }
 }
@@ -208,4 +283,10 @@ impl ConfigFile for DefaultConfigFile {
             None => None,
         }
     }
+    fn event_queue(&self) -> Option<&ConfigFileEventQueue> {
+        match &self.event_queue {
+            Some(event_queue) => Some(event_queue),
+            None => None,
+        }
+    }
 }
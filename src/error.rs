@@ -0,0 +1,86 @@
+use crate::ActionResponse;
+use std::fmt::Display;
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("无效的动作请求：{0}")]
+    BadRequest(String),
+    #[error("不支持的动作：{0}")]
+    UnsupportedAction(String),
+    #[error("无效的动作请求参数：{0}")]
+    BadParam(String),
+    #[error("不支持的动作请求参数：{0}")]
+    UnsupportedParam(String),
+    #[error("不支持的消息段类型：{0}")]
+    UnsupportedSegment(String),
+    #[error("无效的消息段参数：{0}")]
+    BadSegmentData(String),
+    #[error("不支持的消息段参数：{0}")]
+    UnsupportedSegmentData(String),
+
+    #[error("动作处理器实现错误：{0}")]
+    BadHandler(String),
+    #[error("动作处理器运行时错误：{0}")]
+    InternalHandlerError(String),
+
+    #[error("动作执行失败：{message}")]
+    Execution { retcode: i64, message: String },
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+    #[error(transparent)]
+    WebSocket(Box<tokio_tungstenite::tungstenite::Error>),
+
+    #[error("{0}")]
+    Other(String),
+}
+
+impl Error {
+    pub fn msg<S: Display>(msg: S) -> Self {
+        Self::Other(msg.to_string())
+    }
+
+    pub fn execution<S: Display>(retcode: i64, message: S) -> Self {
+        Self::Execution {
+            retcode,
+            message: message.to_string(),
+        }
+    }
+
+    pub fn retcode(&self) -> i64 {
+        match self {
+            Self::BadRequest(_) => 10001,
+            Self::UnsupportedAction(_) => 10002,
+            Self::BadParam(_) => 10003,
+            Self::UnsupportedParam(_) => 10004,
+            Self::UnsupportedSegment(_) => 10005,
+            Self::BadSegmentData(_) => 10006,
+            Self::UnsupportedSegmentData(_) => 10007,
+            Self::BadHandler(_) => 20001,
+            Self::InternalHandlerError(_) => 20002,
+            Self::Execution { retcode, .. } => *retcode,
+            Self::Io(_) => 32000,
+            Self::Http(_) | Self::WebSocket(_) => 33000,
+            Self::Json(_) | Self::Other(_) => 20002,
+        }
+    }
+}
+
+impl From<tokio_tungstenite::tungstenite::Error> for Error {
+    fn from(err: tokio_tungstenite::tungstenite::Error) -> Self {
+        Self::WebSocket(Box::new(err))
+    }
+}
+
+impl From<Error> for ActionResponse {
+    fn from(err: Error) -> Self {
+        Self::failed(err.retcode(), err)
+    }
+}
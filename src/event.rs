@@ -1,7 +1,10 @@
-use crate::{Message, MessageSegment, Result, User};
+use crate::{Message, MessageSegment, Result, Status, User};
 use chrono::{DateTime, Utc};
-use serde::Serialize;
-use std::{collections::HashMap, fmt::Display};
+use serde::{Serialize, Serializer};
+use std::{
+    collections::{btree_map::Entry, BTreeMap, HashMap},
+    fmt::Display,
+};
 
 #[derive(Debug, Clone)]
 pub struct Event {
@@ -16,11 +19,16 @@ pub struct Event {
 
 impl Event {
     pub fn build<S: Display>(id: S) -> EventBuilder {
+        Self::builder().id(id)
+    }
+
+    // 不指定 id，由 OneBot 在推送时生成；平台名称与机器人自身信息同样由 OneBot 填入
+    pub fn builder() -> EventBuilder {
         EventBuilder {
-            id: id.to_string(),
+            id: String::new(),
             platform: String::new(),
             time: Utc::now(),
-            bot_user: User::new("-1"),
+            bot_user: User::new(""),
         }
     }
 
@@ -34,7 +42,7 @@ impl Event {
         self
     }
 
-    pub fn bot_user<S: Display>(mut self, user: User) -> Self {
+    pub fn bot_user(mut self, user: User) -> Self {
         self.bot_user = user;
         self
     }
@@ -45,6 +53,12 @@ impl Event {
     }
 }
 
+impl Serialize for Event {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        EventJson::from(self.clone()).serialize(serializer)
+    }
+}
+
 pub struct EventBuilder {
     id: String,
     platform: String,
@@ -55,6 +69,11 @@ pub struct EventBuilder {
 }
 
 impl EventBuilder {
+    pub fn id<S: Display>(mut self, id: S) -> Self {
+        self.id = id.to_string();
+        self
+    }
+
     pub fn platform<S: Display>(mut self, platform: S) -> Self {
         self.platform = platform.to_string();
         self
@@ -65,7 +84,7 @@ impl EventBuilder {
         self
     }
 
-    pub fn bot_user<S: Display>(mut self, user: User) -> Self {
+    pub fn bot_user(mut self, user: User) -> Self {
         self.bot_user = user;
         self
     }
@@ -100,6 +119,35 @@ impl EventBuilder {
         }
     }
 
+    pub fn friend_request<F: Display, U: Display, C: Display>(
+        self,
+        flag: F,
+        user_id: U,
+        comment: C,
+    ) -> Event {
+        self.request(Request::friend(flag, user_id, comment))
+    }
+
+    pub fn group_join_request<F: Display, G: Display, U: Display, C: Display>(
+        self,
+        flag: F,
+        group_id: G,
+        user_id: U,
+        comment: C,
+    ) -> Event {
+        self.request(Request::group_join(flag, group_id, user_id, comment))
+    }
+
+    pub fn group_invite_request<F: Display, G: Display, U: Display, C: Display>(
+        self,
+        flag: F,
+        group_id: G,
+        user_id: U,
+        comment: C,
+    ) -> Event {
+        self.request(Request::group_invite(flag, group_id, user_id, comment))
+    }
+
     pub fn meta(self, meta: Meta) -> Event {
         Event {
             id: self.id,
@@ -111,7 +159,7 @@ impl EventBuilder {
     }
 }
 
-#[derive(Serialize)]
+#[derive(Default, Serialize)]
 struct EventJson {
     id: String,
     platform: String,
@@ -119,71 +167,243 @@ struct EventJson {
     time: i64,
     self_id: String,
     r#type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     detail_type: Option<String>,
     sub_type: String,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
     message: Option<Vec<MessageSegment>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     message_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     user_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     alt_message: Option<String>,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
     group_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    guild_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    channel_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    operator_id: Option<String>,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
     flag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    interval: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<Status>,
+
+    #[serde(flatten)]
+    extended: BTreeMap<String, String>,
+}
+
+impl EventJson {
+    // 扩展字段以平台名称为前缀，平台名称为空时无法加前缀，直接跳过；
+    // 同名字段以先写入的为准，即发送者的扩展字段优先于群组的扩展字段
+    fn extend(&mut self, extended: HashMap<String, String>) {
+        if self.platform.is_empty() {
+            return;
+        }
+        for (key, value) in extended {
+            match self.extended.entry(format!("{}.{}", self.platform, key)) {
+                Entry::Occupied(entry) => {
+                    log::warn!("扩展字段 {} 重复，忽略值 {}", entry.key(), value);
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(value);
+                }
+            }
+        }
+    }
 }
 
 impl From<Event> for EventJson {
     fn from(event: Event) -> Self {
-        Self {
+        let mut json = Self {
             id: event.id,
             platform: event.platform,
             time: event.time.timestamp(),
             self_id: event.bot_user.id.clone(),
             r#type: event.content.r#type(),
-            detail_type: if let EventContent::Message(message) = &event.content {
+            ..Self::default()
+        };
+
+        match event.content {
+            EventContent::Message(message) => {
                 use crate::message::MessageSource;
-                Some(
+                json.detail_type = Some(
                     match message.source {
                         MessageSource::Private(_) => "private",
                         MessageSource::Group(_) => "group",
                     }
                     .to_string(),
-                )
-            } else {
-                None
-            },
-            sub_type: String::new(),
-            message: if let EventContent::Message(message) = &event.content {
-                Some(message.content.clone())
-            } else {
-                None
-            },
-            message_id: if let EventContent::Message(message) = &event.content {
-                Some(message.id.clone())
-            } else {
-                None
-            },
-            user_id: if let EventContent::Message(message) = &event.content {
-                Some(message.sender.id.clone())
-            } else {
-                None
-            },
-            alt_message: None,
-            group_id: if let EventContent::Message(message) = &event.content {
-                use crate::message::MessageSource;
-                if let MessageSource::Group(group) = &message.source {
-                    Some(group.id.clone())
-                } else {
-                    None
+                );
+                json.sub_type = message.sub_type.clone();
+                json.alt_message = Some(message.alt_message());
+                json.extend(message.sender.extended);
+                if let MessageSource::Group(group) = message.source {
+                    json.group_id = Some(group.id);
+                    json.extend(group.extended);
                 }
-            } else {
-                None
-            },
-            flag: None,
+                json.message = Some(message.content);
+                json.message_id = Some(message.id);
+                json.user_id = Some(message.sender.id);
+            }
+            EventContent::Notice(notice) => {
+                json.detail_type = Some(notice.detail_type().to_string());
+                json.sub_type = notice.sub_type().to_string();
+                match notice {
+                    Notice::FriendIncrease { user_id } | Notice::FriendDecrease { user_id } => {
+                        json.user_id = Some(user_id);
+                    }
+                    Notice::PrivateMessageDelete {
+                        message_id,
+                        user_id,
+                    } => {
+                        json.message_id = Some(message_id);
+                        json.user_id = Some(user_id);
+                    }
+                    Notice::GroupMemberIncrease {
+                        group_id,
+                        user_id,
+                        operator_id,
+                        ..
+                    }
+                    | Notice::GroupMemberDecrease {
+                        group_id,
+                        user_id,
+                        operator_id,
+                        ..
+                    } => {
+                        json.group_id = Some(group_id);
+                        json.user_id = Some(user_id);
+                        json.operator_id = Some(operator_id);
+                    }
+                    Notice::GroupMessageDelete {
+                        group_id,
+                        message_id,
+                        user_id,
+                        operator_id,
+                        ..
+                    } => {
+                        json.group_id = Some(group_id);
+                        json.message_id = Some(message_id);
+                        json.user_id = Some(user_id);
+                        json.operator_id = Some(operator_id);
+                    }
+                    Notice::GuildMemberIncrease {
+                        guild_id,
+                        user_id,
+                        operator_id,
+                        ..
+                    }
+                    | Notice::GuildMemberDecrease {
+                        guild_id,
+                        user_id,
+                        operator_id,
+                        ..
+                    } => {
+                        json.guild_id = Some(guild_id);
+                        json.user_id = Some(user_id);
+                        json.operator_id = Some(operator_id);
+                    }
+                    Notice::ChannelMemberIncrease {
+                        guild_id,
+                        channel_id,
+                        user_id,
+                        operator_id,
+                        ..
+                    }
+                    | Notice::ChannelMemberDecrease {
+                        guild_id,
+                        channel_id,
+                        user_id,
+                        operator_id,
+                        ..
+                    } => {
+                        json.guild_id = Some(guild_id);
+                        json.channel_id = Some(channel_id);
+                        json.user_id = Some(user_id);
+                        json.operator_id = Some(operator_id);
+                    }
+                    Notice::ChannelMessageDelete {
+                        guild_id,
+                        channel_id,
+                        message_id,
+                        user_id,
+                        operator_id,
+                        ..
+                    } => {
+                        json.guild_id = Some(guild_id);
+                        json.channel_id = Some(channel_id);
+                        json.message_id = Some(message_id);
+                        json.user_id = Some(user_id);
+                        json.operator_id = Some(operator_id);
+                    }
+                    Notice::ChannelCreate {
+                        guild_id,
+                        channel_id,
+                        operator_id,
+                    }
+                    | Notice::ChannelDelete {
+                        guild_id,
+                        channel_id,
+                        operator_id,
+                    } => {
+                        json.guild_id = Some(guild_id);
+                        json.channel_id = Some(channel_id);
+                        json.operator_id = Some(operator_id);
+                    }
+                }
+            }
+            EventContent::Request(request) => {
+                json.detail_type = Some(request.detail.detail_type().to_string());
+                json.sub_type = request.detail.sub_type().to_string();
+                json.flag = Some(request.flag);
+                match request.detail {
+                    RequestDetail::Friend { user_id, comment } => {
+                        json.user_id = Some(user_id);
+                        json.comment = Some(comment);
+                    }
+                    RequestDetail::GroupJoin {
+                        group_id,
+                        user_id,
+                        comment,
+                    }
+                    | RequestDetail::GroupInvite {
+                        group_id,
+                        user_id,
+                        comment,
+                    } => {
+                        json.group_id = Some(group_id);
+                        json.user_id = Some(user_id);
+                        json.comment = Some(comment);
+                    }
+                }
+            }
+            EventContent::Meta(meta) => {
+                json.detail_type = Some(meta.detail.detail_type());
+                json.extend(meta.extended);
+                match meta.detail {
+                    MetaDetail::Heartbeat { interval, status } => {
+                        json.interval = Some(interval);
+                        json.status = Some(status);
+                    }
+                }
+            }
         }
+
+        json
     }
 }
 
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone)]
 pub enum EventContent {
     Message(Message),
@@ -204,15 +424,447 @@ impl EventContent {
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct Notice {}
+#[derive(Debug, Clone, PartialEq)]
+pub enum Notice {
+    FriendIncrease {
+        user_id: String,
+    },
+    FriendDecrease {
+        user_id: String,
+    },
+    PrivateMessageDelete {
+        message_id: String,
+        user_id: String,
+    },
+    GroupMemberIncrease {
+        sub_type: MemberIncreaseType,
+        group_id: String,
+        user_id: String,
+        operator_id: String,
+    },
+    GroupMemberDecrease {
+        sub_type: MemberDecreaseType,
+        group_id: String,
+        user_id: String,
+        operator_id: String,
+    },
+    GroupMessageDelete {
+        sub_type: MessageDeleteType,
+        group_id: String,
+        message_id: String,
+        user_id: String,
+        operator_id: String,
+    },
+    GuildMemberIncrease {
+        sub_type: MemberIncreaseType,
+        guild_id: String,
+        user_id: String,
+        operator_id: String,
+    },
+    GuildMemberDecrease {
+        sub_type: MemberDecreaseType,
+        guild_id: String,
+        user_id: String,
+        operator_id: String,
+    },
+    ChannelMemberIncrease {
+        sub_type: MemberIncreaseType,
+        guild_id: String,
+        channel_id: String,
+        user_id: String,
+        operator_id: String,
+    },
+    ChannelMemberDecrease {
+        sub_type: MemberDecreaseType,
+        guild_id: String,
+        channel_id: String,
+        user_id: String,
+        operator_id: String,
+    },
+    ChannelMessageDelete {
+        sub_type: MessageDeleteType,
+        guild_id: String,
+        channel_id: String,
+        message_id: String,
+        user_id: String,
+        operator_id: String,
+    },
+    ChannelCreate {
+        guild_id: String,
+        channel_id: String,
+        operator_id: String,
+    },
+    ChannelDelete {
+        guild_id: String,
+        channel_id: String,
+        operator_id: String,
+    },
+}
 
-#[derive(Debug, Clone)]
+impl Notice {
+    pub fn friend_increase<S: Display>(user_id: S) -> Self {
+        Self::FriendIncrease {
+            user_id: user_id.to_string(),
+        }
+    }
+
+    pub fn friend_decrease<S: Display>(user_id: S) -> Self {
+        Self::FriendDecrease {
+            user_id: user_id.to_string(),
+        }
+    }
+
+    pub fn private_message_delete<S: Display, U: Display>(message_id: S, user_id: U) -> Self {
+        Self::PrivateMessageDelete {
+            message_id: message_id.to_string(),
+            user_id: user_id.to_string(),
+        }
+    }
+
+    pub fn group_member_increase<G: Display, U: Display, O: Display>(
+        sub_type: MemberIncreaseType,
+        group_id: G,
+        user_id: U,
+        operator_id: O,
+    ) -> Self {
+        Self::GroupMemberIncrease {
+            sub_type,
+            group_id: group_id.to_string(),
+            user_id: user_id.to_string(),
+            operator_id: operator_id.to_string(),
+        }
+    }
+
+    pub fn group_member_decrease<G: Display, U: Display, O: Display>(
+        sub_type: MemberDecreaseType,
+        group_id: G,
+        user_id: U,
+        operator_id: O,
+    ) -> Self {
+        Self::GroupMemberDecrease {
+            sub_type,
+            group_id: group_id.to_string(),
+            user_id: user_id.to_string(),
+            operator_id: operator_id.to_string(),
+        }
+    }
+
+    pub fn group_message_delete<G: Display, M: Display, U: Display, O: Display>(
+        sub_type: MessageDeleteType,
+        group_id: G,
+        message_id: M,
+        user_id: U,
+        operator_id: O,
+    ) -> Self {
+        Self::GroupMessageDelete {
+            sub_type,
+            group_id: group_id.to_string(),
+            message_id: message_id.to_string(),
+            user_id: user_id.to_string(),
+            operator_id: operator_id.to_string(),
+        }
+    }
+
+    pub fn guild_member_increase<G: Display, U: Display, O: Display>(
+        sub_type: MemberIncreaseType,
+        guild_id: G,
+        user_id: U,
+        operator_id: O,
+    ) -> Self {
+        Self::GuildMemberIncrease {
+            sub_type,
+            guild_id: guild_id.to_string(),
+            user_id: user_id.to_string(),
+            operator_id: operator_id.to_string(),
+        }
+    }
+
+    pub fn guild_member_decrease<G: Display, U: Display, O: Display>(
+        sub_type: MemberDecreaseType,
+        guild_id: G,
+        user_id: U,
+        operator_id: O,
+    ) -> Self {
+        Self::GuildMemberDecrease {
+            sub_type,
+            guild_id: guild_id.to_string(),
+            user_id: user_id.to_string(),
+            operator_id: operator_id.to_string(),
+        }
+    }
+
+    pub fn channel_member_increase<G: Display, C: Display, U: Display, O: Display>(
+        sub_type: MemberIncreaseType,
+        guild_id: G,
+        channel_id: C,
+        user_id: U,
+        operator_id: O,
+    ) -> Self {
+        Self::ChannelMemberIncrease {
+            sub_type,
+            guild_id: guild_id.to_string(),
+            channel_id: channel_id.to_string(),
+            user_id: user_id.to_string(),
+            operator_id: operator_id.to_string(),
+        }
+    }
+
+    pub fn channel_member_decrease<G: Display, C: Display, U: Display, O: Display>(
+        sub_type: MemberDecreaseType,
+        guild_id: G,
+        channel_id: C,
+        user_id: U,
+        operator_id: O,
+    ) -> Self {
+        Self::ChannelMemberDecrease {
+            sub_type,
+            guild_id: guild_id.to_string(),
+            channel_id: channel_id.to_string(),
+            user_id: user_id.to_string(),
+            operator_id: operator_id.to_string(),
+        }
+    }
+
+    pub fn channel_message_delete<G: Display, C: Display, M: Display, U: Display, O: Display>(
+        sub_type: MessageDeleteType,
+        guild_id: G,
+        channel_id: C,
+        message_id: M,
+        user_id: U,
+        operator_id: O,
+    ) -> Self {
+        Self::ChannelMessageDelete {
+            sub_type,
+            guild_id: guild_id.to_string(),
+            channel_id: channel_id.to_string(),
+            message_id: message_id.to_string(),
+            user_id: user_id.to_string(),
+            operator_id: operator_id.to_string(),
+        }
+    }
+
+    pub fn channel_create<G: Display, C: Display, O: Display>(
+        guild_id: G,
+        channel_id: C,
+        operator_id: O,
+    ) -> Self {
+        Self::ChannelCreate {
+            guild_id: guild_id.to_string(),
+            channel_id: channel_id.to_string(),
+            operator_id: operator_id.to_string(),
+        }
+    }
+
+    pub fn channel_delete<G: Display, C: Display, O: Display>(
+        guild_id: G,
+        channel_id: C,
+        operator_id: O,
+    ) -> Self {
+        Self::ChannelDelete {
+            guild_id: guild_id.to_string(),
+            channel_id: channel_id.to_string(),
+            operator_id: operator_id.to_string(),
+        }
+    }
+
+    pub fn detail_type(&self) -> &'static str {
+        match self {
+            Self::FriendIncrease { .. } => "friend_increase",
+            Self::FriendDecrease { .. } => "friend_decrease",
+            Self::PrivateMessageDelete { .. } => "private_message_delete",
+            Self::GroupMemberIncrease { .. } => "group_member_increase",
+            Self::GroupMemberDecrease { .. } => "group_member_decrease",
+            Self::GroupMessageDelete { .. } => "group_message_delete",
+            Self::GuildMemberIncrease { .. } => "guild_member_increase",
+            Self::GuildMemberDecrease { .. } => "guild_member_decrease",
+            Self::ChannelMemberIncrease { .. } => "channel_member_increase",
+            Self::ChannelMemberDecrease { .. } => "channel_member_decrease",
+            Self::ChannelMessageDelete { .. } => "channel_message_delete",
+            Self::ChannelCreate { .. } => "channel_create",
+            Self::ChannelDelete { .. } => "channel_delete",
+        }
+    }
+
+    pub fn sub_type(&self) -> &'static str {
+        match self {
+            Self::GroupMemberIncrease { sub_type, .. }
+            | Self::GuildMemberIncrease { sub_type, .. }
+            | Self::ChannelMemberIncrease { sub_type, .. } => sub_type.as_str(),
+            Self::GroupMemberDecrease { sub_type, .. }
+            | Self::GuildMemberDecrease { sub_type, .. }
+            | Self::ChannelMemberDecrease { sub_type, .. } => sub_type.as_str(),
+            Self::GroupMessageDelete { sub_type, .. }
+            | Self::ChannelMessageDelete { sub_type, .. } => sub_type.as_str(),
+            _ => "",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MemberIncreaseType {
+    Join,
+    Invite,
+}
+
+impl MemberIncreaseType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Join => "join",
+            Self::Invite => "invite",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MemberDecreaseType {
+    Leave,
+    Kick,
+}
+
+impl MemberDecreaseType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Leave => "leave",
+            Self::Kick => "kick",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MessageDeleteType {
+    Recall,
+    Delete,
+}
+
+impl MessageDeleteType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Recall => "recall",
+            Self::Delete => "delete",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Request {
+    pub detail: RequestDetail,
     flag: String,
 }
 
+impl Request {
+    pub fn friend<F: Display, U: Display, C: Display>(flag: F, user_id: U, comment: C) -> Self {
+        Self {
+            detail: RequestDetail::Friend {
+                user_id: user_id.to_string(),
+                comment: comment.to_string(),
+            },
+            flag: flag.to_string(),
+        }
+    }
+
+    pub fn group_join<F: Display, G: Display, U: Display, C: Display>(
+        flag: F,
+        group_id: G,
+        user_id: U,
+        comment: C,
+    ) -> Self {
+        Self {
+            detail: RequestDetail::GroupJoin {
+                group_id: group_id.to_string(),
+                user_id: user_id.to_string(),
+                comment: comment.to_string(),
+            },
+            flag: flag.to_string(),
+        }
+    }
+
+    pub fn group_invite<F: Display, G: Display, U: Display, C: Display>(
+        flag: F,
+        group_id: G,
+        user_id: U,
+        comment: C,
+    ) -> Self {
+        Self {
+            detail: RequestDetail::GroupInvite {
+                group_id: group_id.to_string(),
+                user_id: user_id.to_string(),
+                comment: comment.to_string(),
+            },
+            flag: flag.to_string(),
+        }
+    }
+
+    pub fn flag(&self) -> &str {
+        &self.flag
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RequestDetail {
+    Friend {
+        user_id: String,
+        comment: String,
+    },
+    GroupJoin {
+        group_id: String,
+        user_id: String,
+        comment: String,
+    },
+    GroupInvite {
+        group_id: String,
+        user_id: String,
+        comment: String,
+    },
+}
+
+impl RequestDetail {
+    pub fn detail_type(&self) -> &'static str {
+        match self {
+            Self::Friend { .. } => "friend",
+            Self::GroupJoin { .. } | Self::GroupInvite { .. } => "group",
+        }
+    }
+
+    pub fn sub_type(&self) -> &'static str {
+        match self {
+            Self::Friend { .. } => "",
+            Self::GroupJoin { .. } => "join",
+            Self::GroupInvite { .. } => "invite",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Meta {
+    pub detail: MetaDetail,
+
     extended: HashMap<String, String>,
 }
+
+impl Meta {
+    pub fn heartbeat(interval: u32, status: Status) -> Self {
+        Self {
+            detail: MetaDetail::Heartbeat { interval, status },
+            extended: HashMap::new(),
+        }
+    }
+
+    pub fn extended<K: Display, V: Display>(mut self, key: K, value: V) -> Self {
+        self.extended.insert(key.to_string(), value.to_string());
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum MetaDetail {
+    Heartbeat { interval: u32, status: Status },
+}
+
+impl MetaDetail {
+    fn detail_type(&self) -> String {
+        match self {
+            Self::Heartbeat { .. } => "heartbeat",
+        }
+        .to_string()
+    }
+}
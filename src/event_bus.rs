@@ -0,0 +1,353 @@
+use crate::{
+    config::{EventQueue, OverflowPolicy},
+    Error, Event, Result, User,
+};
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use std::{
+    collections::VecDeque,
+    future::Future,
+    ops::Deref,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, OnceLock, RwLock, Weak,
+    },
+};
+use tokio::sync::Notify;
+
+#[async_trait]
+pub trait EventGenerator: Send + Sync {
+    async fn generate(&self, sink: EventSink) -> Result<()>;
+}
+
+#[async_trait]
+impl<F, Fut> EventGenerator for F
+where
+    F: Fn(EventSink) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<()>> + Send,
+{
+    async fn generate(&self, sink: EventSink) -> Result<()> {
+        self(sink).await
+    }
+}
+
+// 事件流只能被消费一次，再次运行 OneBot 时返回错误
+pub(crate) struct EventStream {
+    stream: Mutex<Option<Pin<Box<dyn Stream<Item = Event> + Send>>>>,
+}
+
+impl EventStream {
+    pub(crate) fn new<S: 'static + Stream<Item = Event> + Send>(stream: S) -> Self {
+        Self {
+            stream: Mutex::new(Some(Box::pin(stream))),
+        }
+    }
+}
+
+#[async_trait]
+impl EventGenerator for EventStream {
+    async fn generate(&self, sink: EventSink) -> Result<()> {
+        let stream = self.stream.lock().unwrap().take();
+        let mut stream = stream.ok_or_else(|| Error::msg("事件流已被消费，无法再次运行"))?;
+        while let Some(event) = stream.next().await {
+            sink.send(event).await;
+        }
+        Ok(())
+    }
+}
+
+// 向 OneBot 推送事件，可以在任意任务中克隆使用
+#[derive(Clone)]
+pub struct EventSink {
+    event_bus: EventBus,
+}
+
+impl EventSink {
+    pub(crate) fn new(event_bus: EventBus) -> Self {
+        Self { event_bus }
+    }
+
+    // 使用 Block 溢出策略时会等待队列腾出空间
+    pub async fn send(&self, event: Event) {
+        self.event_bus.publish(event).await
+    }
+}
+
+// 在所有订阅者间共享的事件，每种传输格式只在首次使用时序列化一次
+#[derive(Debug, Clone)]
+pub struct SharedEvent {
+    inner: Arc<SharedEventInner>,
+}
+
+#[derive(Debug)]
+struct SharedEventInner {
+    event: Event,
+    json: OnceLock<std::result::Result<Bytes, String>>,
+}
+
+impl SharedEvent {
+    pub fn new(event: Event) -> Self {
+        Self {
+            inner: Arc::new(SharedEventInner {
+                event,
+                json: OnceLock::new(),
+            }),
+        }
+    }
+
+    pub fn event(&self) -> &Event {
+        &self.inner.event
+    }
+
+    // 没有其他订阅者持有该事件时直接取出，否则复制一份
+    pub fn into_event(self) -> Event {
+        match Arc::try_unwrap(self.inner) {
+            Ok(inner) => inner.event,
+            Err(inner) => inner.event.clone(),
+        }
+    }
+
+    // 返回缓存的 JSON，各订阅者共享同一块缓冲区
+    pub fn json(&self) -> Result<Bytes> {
+        self.inner
+            .json
+            .get_or_init(|| {
+                self.inner
+                    .event
+                    .to_json()
+                    .map(Bytes::from)
+                    .map_err(|err| err.to_string())
+            })
+            .clone()
+            .map_err(Error::msg)
+    }
+
+    // WebSocket 文本帧需要独占的 String，每次调用从缓存的 JSON 复制一份
+    pub fn json_string(&self) -> Result<String> {
+        let json = self.json()?;
+        String::from_utf8(json.to_vec()).map_err(|err| Error::msg(err.to_string()))
+    }
+}
+
+impl Deref for SharedEvent {
+    type Target = Event;
+
+    fn deref(&self) -> &Event {
+        &self.inner.event
+    }
+}
+
+impl From<Event> for SharedEvent {
+    fn from(event: Event) -> Self {
+        Self::new(event)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EventStats {
+    pub published: u64,
+    pub dropped: u64,
+}
+
+// 发布前为事件填入未设置的平台名称与机器人自身信息
+#[derive(Default)]
+struct Origin {
+    platform: String,
+    bot_user: Option<User>,
+}
+
+#[derive(Default)]
+struct Inner {
+    origin: RwLock<Origin>,
+    subscribers: Mutex<Vec<Weak<Subscriber>>>,
+    published: AtomicU64,
+    dropped: AtomicU64,
+}
+
+// 事件总线：每个订阅者拥有独立的有界队列，队列满时按各自的溢出策略处理
+#[derive(Clone)]
+pub struct EventBus {
+    inner: Arc<Inner>,
+    name: Arc<str>,
+    queue: EventQueue,
+}
+
+impl EventBus {
+    pub(crate) fn new(queue: EventQueue) -> Self {
+        Self {
+            inner: Arc::new(Inner::default()),
+            name: Arc::from("OneBot"),
+            queue,
+        }
+    }
+
+    // 共享同一总线，但以给定名称和队列配置订阅
+    pub(crate) fn with_queue<S: Into<Arc<str>>>(&self, name: S, queue: EventQueue) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            name: name.into(),
+            queue,
+        }
+    }
+
+    pub(crate) fn set_origin(&self, platform: String, bot_user: Option<User>) {
+        *self.inner.origin.write().unwrap() = Origin { platform, bot_user };
+    }
+
+    pub fn subscribe(&self) -> EventReceiver {
+        let subscriber = Arc::new(Subscriber {
+            name: self.name.clone(),
+            queue: self.queue,
+            events: Mutex::new(VecDeque::with_capacity(self.queue.capacity)),
+            dropped: AtomicU64::new(0),
+            closed: AtomicBool::new(false),
+            readable: Notify::new(),
+            writable: Notify::new(),
+        });
+        let mut subscribers = self.inner.subscribers.lock().unwrap();
+        subscribers.retain(|subscriber| subscriber.strong_count() > 0);
+        subscribers.push(Arc::downgrade(&subscriber));
+        EventReceiver { subscriber }
+    }
+
+    pub fn stats(&self) -> EventStats {
+        EventStats {
+            published: self.inner.published.load(Ordering::Relaxed),
+            dropped: self.inner.dropped.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) async fn publish(&self, mut event: Event) {
+        {
+            let origin = self.inner.origin.read().unwrap();
+            if event.platform.is_empty() {
+                event.platform = origin.platform.clone();
+            }
+            if event.bot_user.id.is_empty() {
+                if let Some(bot_user) = &origin.bot_user {
+                    event.bot_user = bot_user.clone();
+                }
+            }
+        }
+        if event.id.is_empty() {
+            event.id = uuid::Uuid::new_v4().to_string();
+        }
+        let event = SharedEvent::new(event);
+        self.inner.published.fetch_add(1, Ordering::Relaxed);
+        let subscribers: Vec<Arc<Subscriber>> = self
+            .inner
+            .subscribers
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .collect();
+        for subscriber in subscribers {
+            if !subscriber.push(event.clone()).await {
+                self.inner.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+struct Subscriber {
+    name: Arc<str>,
+    queue: EventQueue,
+    events: Mutex<VecDeque<SharedEvent>>,
+    dropped: AtomicU64,
+    closed: AtomicBool,
+    readable: Notify,
+    writable: Notify,
+}
+
+impl Subscriber {
+    // 返回 false 表示有事件被丢弃
+    async fn push(&self, event: SharedEvent) -> bool {
+        loop {
+            let writable = self.writable.notified();
+            {
+                let mut events = self.events.lock().unwrap();
+                if self.closed.load(Ordering::Acquire) {
+                    return true;
+                }
+                if events.len() < self.queue.capacity {
+                    events.push_back(event);
+                    self.readable.notify_one();
+                    return true;
+                }
+                match self.queue.overflow {
+                    OverflowPolicy::DropOldest => {
+                        events.pop_front();
+                        events.push_back(event);
+                        self.readable.notify_one();
+                        self.record_dropped("最早");
+                        return false;
+                    }
+                    OverflowPolicy::DropNewest => {
+                        self.record_dropped("最新");
+                        return false;
+                    }
+                    OverflowPolicy::Block => {}
+                }
+            }
+            writable.await;
+        }
+    }
+
+    fn pop(&self) -> Option<SharedEvent> {
+        let event = self.events.lock().unwrap().pop_front();
+        if event.is_some() {
+            self.writable.notify_one();
+        }
+        event
+    }
+
+    fn record_dropped(&self, which: &str) {
+        let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+        // 按 2 的幂次记录，避免突发时刷屏
+        if dropped.is_power_of_two() {
+            log::warn!(
+                "{} 的事件队列已满（容量 {}），丢弃{}的事件（累计丢弃 {} 个）",
+                self.name,
+                self.queue.capacity,
+                which,
+                dropped
+            );
+        }
+    }
+}
+
+pub struct EventReceiver {
+    subscriber: Arc<Subscriber>,
+}
+
+impl EventReceiver {
+    pub async fn recv(&mut self) -> SharedEvent {
+        loop {
+            let readable = self.subscriber.readable.notified();
+            if let Some(event) = self.subscriber.pop() {
+                return event;
+            }
+            readable.await;
+        }
+    }
+
+    pub fn try_recv(&mut self) -> Option<SharedEvent> {
+        self.subscriber.pop()
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.subscriber.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for EventReceiver {
+    fn drop(&mut self) {
+        // 唤醒因 Block 策略等待的发布者
+        let _events = self.subscriber.events.lock().unwrap();
+        self.subscriber.closed.store(true, Ordering::Release);
+        self.subscriber.writable.notify_waiters();
+    }
+}
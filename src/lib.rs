@@ -1,42 +1,60 @@
-use config::Config;
+use config::{Config, EventQueue};
 use serde::Serialize;
 use std::{
     collections::HashMap,
     fmt::{Debug, Display},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
 };
-// use thiserror::Error;
-use tokio::sync::broadcast::{Receiver, Sender};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 
 pub struct OneBot {
     pub platform: String,
+    pub impl_name: String,
+    pub impl_version: String,
     pub self_user: Option<User>,
     config: Option<Config>,
 
-    event_generator: Box<dyn Fn(Sender<Event>) -> Result<()>>,
+    event_generator: Option<Arc<dyn EventGenerator>>,
     action_handlers: HashMap<String, Action>,
 
     comms: HashMap<String, Box<dyn Comm>>,
+    comm_event_queues: HashMap<String, EventQueue>,
 
-    event_sender: Sender<Event>,
-    _event_default_receiver: Receiver<Event>,
+    event_bus: EventBus,
+
+    status_flags: Arc<StatusFlags>,
+    shutdown_token: CancellationToken,
 }
 
 impl OneBot {
     pub fn new<S: Display>(platform: S) -> Self {
-        let (event_sender, _event_default_receiver) = tokio::sync::broadcast::channel(1);
-
         Self {
             platform: platform.to_string(),
+            impl_name: "libonebot".to_string(),
+            impl_version: env!("CARGO_PKG_VERSION").to_string(),
             self_user: None,
             config: None,
-            event_generator: Box::new(Self::default_event_generator),
+            event_generator: None,
             action_handlers: HashMap::new(),
             comms: HashMap::new(),
-            event_sender,
-            _event_default_receiver,
+            comm_event_queues: HashMap::new(),
+            event_bus: EventBus::new(Config::new().event_queue),
+            status_flags: Arc::new(StatusFlags::new()),
+            shutdown_token: CancellationToken::new(),
         }
     }
 
+    pub fn set_impl<S: Display, V: Display>(&mut self, name: S, version: V) -> &mut Self {
+        self.impl_name = name.to_string();
+        self.impl_version = version.to_string();
+        self
+    }
+
     pub fn set_self_id<S: Display>(&mut self, id: S) -> &mut Self {
         self.self_user = Some(User::new(id));
         self
@@ -119,6 +137,41 @@ impl OneBot {
         }
     }
 
+    pub fn event_queue(&self) -> EventQueue {
+        match &self.config {
+            Some(config) => config.event_queue,
+            None => Config::new().event_queue,
+        }
+    }
+
+    pub fn set_event_queue(&mut self, event_queue: EventQueue) -> &mut Self {
+        let mut config = Config::new();
+        if let Some(c) = &self.config {
+            config = c.clone();
+        }
+        config.event_queue = event_queue;
+        self.config = Some(config);
+        self
+    }
+
+    pub fn event_stats(&self) -> EventStats {
+        self.event_bus.stats()
+    }
+
+    // 推送的事件中未设置的平台名称、机器人自身信息会被填入，未提供 id 时自动生成
+    pub fn event_sink(&self) -> EventSink {
+        EventSink::new(self.event_bus.clone())
+    }
+
+    pub fn status(&self) -> Status {
+        self.status_flags.load()
+    }
+
+    pub fn set_online(&self, online: bool) -> &Self {
+        self.status_flags.online.store(online, Ordering::SeqCst);
+        self
+    }
+
     pub fn log_to_stderr(&mut self) -> &mut Self {
         let mut config = Config::new();
         if let Some(c) = &self.config {
@@ -179,35 +232,83 @@ impl OneBot {
         self
     }
 
+    // 为该通信方式的每个事件订阅者单独指定事件队列，未指定时使用全局配置
+    pub fn add_comm_with_event_queue<S: Display, C: 'static + Comm>(
+        &mut self,
+        name: &S,
+        comm: C,
+        event_queue: EventQueue,
+    ) -> &mut Self {
+        self.comm_event_queues.insert(name.to_string(), event_queue);
+        self.add_comm_box(name, Box::new(comm));
+        self
+    }
+
     pub fn init_from_file<F: ConfigFile>(&mut self, config_file: F) -> Result<&mut Self> {
         self.set_config(Config::from_config_file(&config_file)?);
 
         self.comms = HashMap::new();
+        self.comm_event_queues = HashMap::new();
 
         if let Some(comm_methods) = config_file.comm_methods() {
             for (comm_name, comm_method) in comm_methods {
                 self.add_comm_box(&comm_name, comm::from_config_file_comm_method(comm_method)?);
+                if comm_method.queue_capacity.is_some() || comm_method.queue_overflow.is_some() {
+                    let event_queue = self.event_queue().with_overrides(
+                        comm_method.queue_capacity,
+                        comm_method.queue_overflow.as_deref(),
+                    )?;
+                    self.comm_event_queues
+                        .insert(comm_name.to_string(), event_queue);
+                }
             }
         }
 
         Ok(self)
     }
 
-    fn start_comm_methods(&self) {
-        for (_, comm) in self.comms.iter() {
+    fn start_comm_methods(
+        &self,
+        action_handlers: &HashMap<String, Action>,
+        tasks: &mut Vec<JoinHandle<()>>,
+    ) {
+        for (name, comm) in self.comms.iter() {
+            let name = name.clone();
             let comm = comm.clone();
-            let action_handlers = self.action_handlers.clone();
-            let event_sender = self.event_sender.clone();
-            let platform = self.platform.clone();
-            tokio::spawn(async move {
-                comm.start(action_handlers, event_sender, platform.clone())
-                    .await
-                    .unwrap();
-            });
+            let shutdown = self.shutdown_token.child_token();
+            let event_queue = self
+                .comm_event_queues
+                .get(&name)
+                .copied()
+                .unwrap_or_else(|| self.event_queue());
+            let context = comm::CommContext {
+                action_handlers: action_handlers.clone(),
+                event_bus: self.event_bus.with_queue(name.as_str(), event_queue),
+                platform: self.platform.clone(),
+                self_id: self
+                    .self_user
+                    .as_ref()
+                    .map(|user| user.id.clone())
+                    .unwrap_or_default(),
+                impl_name: self.impl_name.clone(),
+                impl_version: self.impl_version.clone(),
+                access_token: self.access_token(),
+                shutdown: shutdown.clone(),
+            };
+            let status_flags = self.status_flags.clone();
+            tasks.push(tokio::spawn(async move {
+                let result = comm.start(context).await;
+                if !shutdown.is_cancelled() {
+                    status_flags.good.store(false, Ordering::SeqCst);
+                }
+                if let Err(err) = result {
+                    log::error!("通信方式 {} 异常退出：{}", name, err);
+                }
+            }));
         }
     }
 
-    pub async fn run(&mut self) -> Result<()> {
+    pub async fn run(&mut self) -> Result<OneBotHandle> {
         if self.platform.is_empty() {
             return Err(Error::msg("必须提供 OneBot 平台名称"));
         }
@@ -218,6 +319,9 @@ impl OneBot {
         let heartbeat;
         if let Some(config) = &self.config {
             heartbeat = config.heartbeat;
+            if heartbeat == Some(0) {
+                return Err(Error::msg("心跳间隔必须大于 0"));
+            }
 
             let mut dispatch = fern::Dispatch::new().format(|out, message, record| {
                 out.finish(format_args!(
@@ -235,55 +339,177 @@ impl OneBot {
             if let Some(path) = &config.log.path {
                 dispatch = dispatch.chain(fern::log_file(path)?);
             }
-            dispatch.level(config.log.level).apply()?;
+            if dispatch.level(config.log.level).apply().is_err() {
+                log::warn!("日志系统已初始化，忽略 OneBot 日志配置");
+            }
         } else {
             return Err(Error::msg("必须提供 OneBot 配置"));
         }
 
-        // context.withCancel
+        self.shutdown_token = CancellationToken::new();
+        self.status_flags.good.store(true, Ordering::SeqCst);
+        self.event_bus
+            .set_origin(self.platform.clone(), self.self_user.clone());
+        let mut tasks = Vec::new();
 
-        self.start_comm_methods();
-        if let Some(_heartbeat) = heartbeat {
-            self.heartbeat();
+        let action_handlers = self.action_handlers_with_builtins();
+        self.start_comm_methods(&action_handlers, &mut tasks);
+        if let Some(interval) = heartbeat {
+            tasks.push(self.heartbeat(interval));
         }
 
         log::info!("OneBot 已启动");
 
-        (self.event_generator)(self.event_sender.clone())?;
+        if let Some(event_generator) = self.event_generator.clone() {
+            tasks.push(self.start_event_generator(event_generator));
+        }
 
-        Ok(())
+        Ok(OneBotHandle {
+            shutdown_token: self.shutdown_token.clone(),
+            tasks,
+        })
     }
 
-    fn heartbeat(&self) {}
+    fn start_event_generator(&self, event_generator: Arc<dyn EventGenerator>) -> JoinHandle<()> {
+        let sink = self.event_sink();
+        let shutdown = self.shutdown_token.clone();
+        tokio::spawn(async move {
+            // 关闭时直接取消事件生成器
+            tokio::select! {
+                _ = shutdown.cancelled() => {}
+                result = event_generator.generate(sink) => {
+                    if let Err(err) = result {
+                        log::error!("事件生成器异常退出：{}", err);
+                    }
+                }
+            }
+        })
+    }
+
+    fn heartbeat(&self, interval: u32) -> JoinHandle<()> {
+        let sink = self.event_sink();
+        let status_flags = self.status_flags.clone();
+        let shutdown = self.shutdown_token.clone();
+        let period = Duration::from_millis(interval.into());
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            loop {
+                tokio::select! {
+                    _ = shutdown.cancelled() => break,
+                    _ = ticker.tick() => {}
+                }
+                let status = status_flags.load();
+                let event = Event::builder().meta(event::Meta::heartbeat(interval, status));
+                tokio::select! {
+                    _ = shutdown.cancelled() => break,
+                    _ = sink.send(event) => {}
+                }
+            }
+        })
+    }
 
     pub fn shutdown(&self) {
-        log::info!("OneBot 已关闭");
+        self.shutdown_token.cancel();
     }
 
-    pub fn register_event_generator<F: 'static + Fn(Sender<Event>) -> Result<()>>(
+    pub fn register_event_generator<G: 'static + EventGenerator>(&mut self, event_generator: G) {
+        self.event_generator = Some(Arc::new(event_generator));
+    }
+
+    pub fn register_event_stream<S: 'static + futures::Stream<Item = Event> + Send>(
         &mut self,
-        event_generator: F,
+        event_stream: S,
     ) {
-        self.event_generator = Box::new(event_generator);
+        self.event_generator = Some(Arc::new(event_bus::EventStream::new(event_stream)));
     }
 
-    fn default_event_generator(_: Sender<Event>) -> Result<()> {
-        loop {
-            panic!()
+    fn action_handlers_with_builtins(&self) -> HashMap<String, Action> {
+        let mut action_handlers = self.action_handlers.clone();
+
+        let status_flags = self.status_flags.clone();
+        action_handlers
+            .entry("get_status".to_string())
+            .or_insert_with(|| {
+                Action::new(move |_| {
+                    let status = status_flags.load();
+                    async move { ActionResponse::ok(serde_json::json!(status)) }
+                })
+            });
+
+        let version = serde_json::json!({
+            "impl": self.impl_name,
+            "platform": self.platform,
+            "version": self.impl_version,
+            "onebot_version": "12",
+        });
+        action_handlers
+            .entry("get_version".to_string())
+            .or_insert_with(|| {
+                Action::new(move |_| {
+                    let version = version.clone();
+                    async move { ActionResponse::ok(version) }
+                })
+            });
+
+        if !action_handlers.contains_key("get_supported_actions") {
+            let mut supported_actions: Vec<String> = action_handlers.keys().cloned().collect();
+            supported_actions.push("get_supported_actions".to_string());
+            supported_actions.sort();
+            let supported_actions = serde_json::json!(supported_actions);
+            action_handlers.insert(
+                "get_supported_actions".to_string(),
+                Action::new(move |_| {
+                    let supported_actions = supported_actions.clone();
+                    async move { ActionResponse::ok(supported_actions) }
+                }),
+            );
         }
+
+        action_handlers
     }
 
-    pub fn register_action_handler<S: Display>(
+    pub fn register_action_handler<S: Display, H: 'static + ActionHandler>(
         &mut self,
         name: S,
-        action: fn(_: serde_json::Value) -> String,
+        handler: H,
     ) -> &mut Self {
         self.action_handlers
-            .insert(name.to_string(), Action::from(action));
+            .insert(name.to_string(), Action::new(handler));
         self
     }
 }
 
+pub struct OneBotHandle {
+    shutdown_token: CancellationToken,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl OneBotHandle {
+    pub fn shutdown_token(&self) -> CancellationToken {
+        self.shutdown_token.clone()
+    }
+
+    pub fn is_shutdown(&self) -> bool {
+        self.shutdown_token.is_cancelled()
+    }
+
+    pub async fn shutdown(self) {
+        self.shutdown_token.cancel();
+        self.wait().await;
+    }
+
+    pub async fn wait(self) {
+        self.shutdown_token.cancelled().await;
+        for task in futures::future::join_all(self.tasks).await {
+            if let Err(err) = task {
+                log::error!("OneBot 任务异常退出：{}", err);
+            }
+        }
+        log::info!("OneBot 已关闭");
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct User {
     id: String,
@@ -305,30 +531,81 @@ impl User {
             extended: HashMap::new(),
         }
     }
+
+    pub fn extended<K: Display, V: Display>(mut self, key: K, value: V) -> Self {
+        self.extended.insert(key.to_string(), value.to_string());
+        self
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Group {
     id: String,
 
-    name: String,
+    pub name: String,
 
     extended: HashMap<String, String>,
 }
 
+impl Group {
+    pub fn new<S: Display>(id: S) -> Self {
+        Self {
+            id: id.to_string(),
+            name: String::new(),
+            extended: HashMap::new(),
+        }
+    }
+
+    pub fn extended<K: Display, V: Display>(mut self, key: K, value: V) -> Self {
+        self.extended.insert(key.to_string(), value.to_string());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Status {
+    pub good: bool,
+    pub online: bool,
+}
+
+struct StatusFlags {
+    good: AtomicBool,
+    online: AtomicBool,
+}
+
+impl StatusFlags {
+    fn new() -> Self {
+        Self {
+            good: AtomicBool::new(true),
+            online: AtomicBool::new(true),
+        }
+    }
+
+    fn load(&self) -> Status {
+        Status {
+            good: self.good.load(Ordering::SeqCst),
+            online: self.online.load(Ordering::SeqCst),
+        }
+    }
+}
+
 pub mod action;
-pub use action::Action;
+pub use action::{Action, ActionHandler, ActionResponse};
 
 pub mod comm;
-pub use comm::Comm;
+pub use comm::{Comm, CommContext};
 
 pub mod config;
 pub use config::ConfigFile;
 
+pub mod error;
+pub use error::{Error, Result};
+
 pub mod event;
-pub use event::{Event, EventContent};
+pub use event::{Event, EventContent, Meta, Notice, Request};
+
+pub mod event_bus;
+pub use event_bus::{EventBus, EventGenerator, EventReceiver, EventSink, EventStats, SharedEvent};
 
 pub mod message;
 pub use message::{Message, MessageSegment};
-
-pub use anyhow::{Error, Result};
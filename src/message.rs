@@ -1,10 +1,11 @@
-use crate::{Group, User};
-use serde::Serialize;
-use std::fmt::Display;
+use crate::{Error, Group, Result, User};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{convert::TryFrom, fmt::Display};
 
 #[derive(Debug, Clone)]
 pub struct Message {
     pub id: String,
+    pub sub_type: String,
 
     pub source: MessageSource,
     pub sender: User,
@@ -16,12 +17,14 @@ impl Message {
     pub fn build<S: Display>(id: S) -> MessageBuilder {
         MessageBuilder {
             id: id.to_string(),
+            sub_type: String::new(),
             content: Vec::new(),
         }
     }
     pub fn private(self, user: User) -> Message {
         Message {
             id: self.id,
+            sub_type: self.sub_type,
             source: MessageSource::Private(user.clone()),
             sender: user,
             content: self.content,
@@ -31,65 +34,116 @@ impl Message {
     pub fn group(self, group: Group, sender: User) -> Message {
         Message {
             id: self.id,
+            sub_type: self.sub_type,
             source: MessageSource::Group(group),
             sender,
             content: self.content,
         }
     }
 
+    pub fn alt_message(&self) -> String {
+        self.content.iter().map(MessageSegment::alt).collect()
+    }
+
+    pub fn sub_type<S: Display>(mut self, sub_type: S) -> Self {
+        self.sub_type = sub_type.to_string();
+        self
+    }
+
     pub fn text<S: Display>(mut self, text: S) -> Self {
-        self.content.push(MessageSegment::Text(text.to_string()));
+        self.content.push(MessageSegment::Text {
+            text: text.to_string(),
+        });
         self
     }
 
-    pub fn emoji<S: Display>(mut self, emoji: S) -> Self {
-        self.content.push(MessageSegment::Emoji(emoji.to_string()));
+    pub fn mention<S: Display>(mut self, user_id: S) -> Self {
+        self.content.push(MessageSegment::Mention {
+            user_id: user_id.to_string(),
+        });
         self
     }
 
-    pub fn image(mut self, image: Media) -> Self {
-        self.content.push(MessageSegment::Image(image));
+    pub fn mention_all(mut self) -> Self {
+        self.content.push(MessageSegment::MentionAll {});
         self
     }
 
-    pub fn record(mut self, record: Media) -> Self {
-        self.content.push(MessageSegment::Record(record));
+    pub fn image<S: Display>(mut self, file_id: S) -> Self {
+        self.content.push(MessageSegment::Image {
+            file_id: file_id.to_string(),
+        });
         self
     }
 
-    pub fn video(mut self, video: Media) -> Self {
-        self.content.push(MessageSegment::Video(video));
+    pub fn voice<S: Display>(mut self, file_id: S) -> Self {
+        self.content.push(MessageSegment::Voice {
+            file_id: file_id.to_string(),
+        });
         self
     }
 
-    pub fn at(mut self, user: User) -> Self {
-        self.content.push(MessageSegment::At(user));
+    pub fn audio<S: Display>(mut self, file_id: S) -> Self {
+        self.content.push(MessageSegment::Audio {
+            file_id: file_id.to_string(),
+        });
         self
     }
 
-    pub fn location(mut self, lat: f64, lon: f64) -> Self {
-        self.content.push(MessageSegment::Location(lat, lon));
+    pub fn video<S: Display>(mut self, file_id: S) -> Self {
+        self.content.push(MessageSegment::Video {
+            file_id: file_id.to_string(),
+        });
         self
     }
 
-    pub fn reply<S: Display>(mut self, id: S) -> Self {
-        self.content.push(MessageSegment::Reply(id.to_string()));
+    pub fn file<S: Display>(mut self, file_id: S) -> Self {
+        self.content.push(MessageSegment::File {
+            file_id: file_id.to_string(),
+        });
         self
     }
 
-    pub fn forward<S: Display>(mut self, id: S) -> Self {
-        self.content.push(MessageSegment::Forward(id.to_string()));
+    pub fn location<S: Display, T: Display>(
+        mut self,
+        latitude: f64,
+        longitude: f64,
+        title: S,
+        content: T,
+    ) -> Self {
+        self.content.push(MessageSegment::Location {
+            latitude,
+            longitude,
+            title: title.to_string(),
+            content: content.to_string(),
+        });
         self
     }
 
-    pub fn custom<S: Display>(mut self, s: S) -> Self {
-        self.content.push(MessageSegment::Custom(s.to_string()));
+    pub fn reply<S: Display, U: Display>(mut self, message_id: S, user_id: U) -> Self {
+        self.content.push(MessageSegment::Reply {
+            message_id: message_id.to_string(),
+            user_id: Some(user_id.to_string()),
+        });
+        self
+    }
+
+    pub fn extended<S: Display>(
+        mut self,
+        r#type: S,
+        data: serde_json::Map<String, serde_json::Value>,
+    ) -> Self {
+        self.content.push(MessageSegment::Extended(ExtendedSegment {
+            r#type: r#type.to_string(),
+            data,
+        }));
         self
     }
 }
 
 pub struct MessageBuilder {
     id: String,
+    sub_type: String,
 
     content: Vec<MessageSegment>,
 }
@@ -98,6 +152,7 @@ impl MessageBuilder {
     pub fn private(self, user: User) -> Message {
         Message {
             id: self.id,
+            sub_type: self.sub_type,
             source: MessageSource::Private(user.clone()),
             sender: user,
             content: self.content,
@@ -107,59 +162,105 @@ impl MessageBuilder {
     pub fn group(self, group: Group, sender: User) -> Message {
         Message {
             id: self.id,
+            sub_type: self.sub_type,
             source: MessageSource::Group(group),
             sender,
             content: self.content,
         }
     }
 
+    pub fn sub_type<S: Display>(mut self, sub_type: S) -> Self {
+        self.sub_type = sub_type.to_string();
+        self
+    }
+
     pub fn text<S: Display>(mut self, text: S) -> Self {
-        self.content.push(MessageSegment::Text(text.to_string()));
+        self.content.push(MessageSegment::Text {
+            text: text.to_string(),
+        });
+        self
+    }
+
+    pub fn mention<S: Display>(mut self, user_id: S) -> Self {
+        self.content.push(MessageSegment::Mention {
+            user_id: user_id.to_string(),
+        });
         self
     }
 
-    pub fn emoji<S: Display>(mut self, emoji: S) -> Self {
-        self.content.push(MessageSegment::Emoji(emoji.to_string()));
+    pub fn mention_all(mut self) -> Self {
+        self.content.push(MessageSegment::MentionAll {});
         self
     }
 
-    pub fn image(mut self, image: Media) -> Self {
-        self.content.push(MessageSegment::Image(image));
+    pub fn image<S: Display>(mut self, file_id: S) -> Self {
+        self.content.push(MessageSegment::Image {
+            file_id: file_id.to_string(),
+        });
         self
     }
 
-    pub fn record(mut self, record: Media) -> Self {
-        self.content.push(MessageSegment::Record(record));
+    pub fn voice<S: Display>(mut self, file_id: S) -> Self {
+        self.content.push(MessageSegment::Voice {
+            file_id: file_id.to_string(),
+        });
         self
     }
 
-    pub fn video(mut self, video: Media) -> Self {
-        self.content.push(MessageSegment::Video(video));
+    pub fn audio<S: Display>(mut self, file_id: S) -> Self {
+        self.content.push(MessageSegment::Audio {
+            file_id: file_id.to_string(),
+        });
         self
     }
 
-    pub fn at(mut self, user: User) -> Self {
-        self.content.push(MessageSegment::At(user));
+    pub fn video<S: Display>(mut self, file_id: S) -> Self {
+        self.content.push(MessageSegment::Video {
+            file_id: file_id.to_string(),
+        });
         self
     }
 
-    pub fn location(mut self, lat: f64, lon: f64) -> Self {
-        self.content.push(MessageSegment::Location(lat, lon));
+    pub fn file<S: Display>(mut self, file_id: S) -> Self {
+        self.content.push(MessageSegment::File {
+            file_id: file_id.to_string(),
+        });
         self
     }
 
-    pub fn reply<S: Display>(mut self, id: S) -> Self {
-        self.content.push(MessageSegment::Reply(id.to_string()));
+    pub fn location<S: Display, T: Display>(
+        mut self,
+        latitude: f64,
+        longitude: f64,
+        title: S,
+        content: T,
+    ) -> Self {
+        self.content.push(MessageSegment::Location {
+            latitude,
+            longitude,
+            title: title.to_string(),
+            content: content.to_string(),
+        });
         self
     }
 
-    pub fn forward<S: Display>(mut self, id: S) -> Self {
-        self.content.push(MessageSegment::Forward(id.to_string()));
+    pub fn reply<S: Display, U: Display>(mut self, message_id: S, user_id: U) -> Self {
+        self.content.push(MessageSegment::Reply {
+            message_id: message_id.to_string(),
+            user_id: Some(user_id.to_string()),
+        });
         self
     }
 
-    pub fn custom<S: Display>(mut self, s: S) -> Self {
-        self.content.push(MessageSegment::Custom(s.to_string()));
+    pub fn extended<S: Display>(
+        mut self,
+        r#type: S,
+        data: serde_json::Map<String, serde_json::Value>,
+    ) -> Self {
+        self.content.push(MessageSegment::Extended(ExtendedSegment {
+            r#type: r#type.to_string(),
+            data,
+        }));
         self
     }
 }
@@ -170,37 +271,174 @@ pub enum MessageSource {
     Group(Group),
 }
 
-#[derive(Debug, Clone, Serialize)]
+// remote = "Self" 将派生代码生成为关联函数，仅用于标准消息段，由下方手写的 Serialize/Deserialize 调用
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(
+    remote = "Self",
+    tag = "type",
+    content = "data",
+    rename_all = "snake_case"
+)]
 pub enum MessageSegment {
-    Text(String),
-    Emoji(String),
-    Image(Media),
-    Record(Media),
-    Video(Media),
-    At(User),
-    Location(f64, f64), // lat, lon
-    Reply(String),
-    Forward(String),
-    Custom(String),
+    Text {
+        text: String,
+    },
+    Mention {
+        user_id: String,
+    },
+    MentionAll {},
+    Image {
+        file_id: String,
+    },
+    Voice {
+        file_id: String,
+    },
+    Audio {
+        file_id: String,
+    },
+    Video {
+        file_id: String,
+    },
+    File {
+        file_id: String,
+    },
+    Location {
+        latitude: f64,
+        longitude: f64,
+        title: String,
+        content: String,
+    },
+    Reply {
+        message_id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        user_id: Option<String>,
+    },
+    #[serde(skip)]
+    Extended(ExtendedSegment),
+}
+
+impl Serialize for MessageSegment {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            Self::Extended(extended) => extended.serialize(serializer),
+            _ => MessageSegment::serialize(self, serializer),
+        }
+    }
 }
 
-#[derive(Debug, Clone, Serialize)]
-pub enum Media {
-    File(String),
-    URL(String), // url, cache, proxy, timeout
-    Base64(String),
+// 先读取 type，再按类型解析 data，使标准消息段的字段错误能够返回给调用方
+impl<'de> Deserialize<'de> for MessageSegment {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let raw = RawSegment::deserialize(deserializer)?;
+        if !STANDARD_SEGMENT_TYPES.contains(&raw.r#type.as_str()) {
+            return Ok(Self::Extended(ExtendedSegment {
+                r#type: raw.r#type,
+                data: raw.data,
+            }));
+        }
+        let value = serde_json::json!({"type": raw.r#type, "data": raw.data});
+        MessageSegment::deserialize(value).map_err(|err| {
+            serde::de::Error::custom(format!("{} 消息段参数错误：{}", raw.r#type, err))
+        })
+    }
 }
 
-impl Media {
-    pub fn new_file<S: Display>(file: S) -> Self {
-        Self::File(file.to_string())
+impl MessageSegment {
+    pub fn r#type(&self) -> &str {
+        match self {
+            Self::Text { .. } => "text",
+            Self::Mention { .. } => "mention",
+            Self::MentionAll {} => "mention_all",
+            Self::Image { .. } => "image",
+            Self::Voice { .. } => "voice",
+            Self::Audio { .. } => "audio",
+            Self::Video { .. } => "video",
+            Self::File { .. } => "file",
+            Self::Location { .. } => "location",
+            Self::Reply { .. } => "reply",
+            Self::Extended(extended) => &extended.r#type,
+        }
     }
 
-    pub fn new_url<S: Display>(url: S) -> Self {
-        Self::URL(url.to_string())
+    pub fn alt(&self) -> String {
+        match self {
+            Self::Text { text } => text.clone(),
+            Self::Mention { user_id } => format!("@{}", user_id),
+            Self::MentionAll {} => "@全体成员".to_string(),
+            Self::Image { .. } => "[图片]".to_string(),
+            Self::Voice { .. } => "[语音]".to_string(),
+            Self::Audio { .. } => "[音频]".to_string(),
+            Self::Video { .. } => "[视频]".to_string(),
+            Self::File { .. } => "[文件]".to_string(),
+            Self::Location { title, .. } => format!("[位置]{}", title),
+            Self::Reply { .. } => String::new(),
+            Self::Extended(extended) => format!("[{}]", extended.r#type),
+        }
     }
+}
+
+const STANDARD_SEGMENT_TYPES: &[&str] = &[
+    "text",
+    "mention",
+    "mention_all",
+    "image",
+    "voice",
+    "audio",
+    "video",
+    "file",
+    "location",
+    "reply",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "RawSegment")]
+pub struct ExtendedSegment {
+    pub r#type: String,
+    pub data: serde_json::Map<String, serde_json::Value>,
+}
 
-    pub fn new_base64<S: Display>(base64: S) -> Self {
-        Self::Base64(base64.to_string())
+// 所有消息段的 data 均可省略，视为空对象
+#[derive(Deserialize)]
+struct RawSegment {
+    r#type: String,
+    #[serde(default)]
+    data: serde_json::Map<String, serde_json::Value>,
+}
+
+impl TryFrom<RawSegment> for ExtendedSegment {
+    type Error = String;
+
+    fn try_from(raw: RawSegment) -> std::result::Result<Self, Self::Error> {
+        if STANDARD_SEGMENT_TYPES.contains(&raw.r#type.as_str()) {
+            return Err(format!(
+                "{} 为标准消息段类型，不能作为扩展消息段",
+                raw.r#type
+            ));
+        }
+        Ok(Self {
+            r#type: raw.r#type,
+            data: raw.data,
+        })
+    }
+}
+
+pub fn segments_from_value(value: serde_json::Value) -> Result<Vec<MessageSegment>> {
+    match value {
+        serde_json::Value::String(text) => Ok(vec![MessageSegment::Text { text }]),
+        serde_json::Value::Array(segments) => segments
+            .into_iter()
+            .map(|segment| {
+                serde_json::from_value(segment)
+                    .map_err(|err| Error::BadSegmentData(err.to_string()))
+            })
+            .collect(),
+        _ => Err(Error::BadParam("消息必须为消息段数组或字符串".to_string())),
     }
 }
+
+pub fn deserialize_segments<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Vec<MessageSegment>, D::Error> {
+    let value = serde_json::Value::deserialize(deserializer)?;
+    segments_from_value(value).map_err(serde::de::Error::custom)
+}
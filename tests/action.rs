@@ -0,0 +1,151 @@
+mod common;
+
+use common::{start_http, test_onebot};
+use libonebot::ActionResponse;
+use serde_json::{json, Value};
+
+async fn post(url: &str, body: Value) -> Value {
+    let text = reqwest::Client::new()
+        .post(url)
+        .body(body.to_string())
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    serde_json::from_str(&text).unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn http_responds_with_action_response_envelope() {
+    let mut onebot = test_onebot();
+    onebot.register_action_handler("echo", |params: Value| async move {
+        ActionResponse::ok(params["message"].clone())
+    });
+    onebot.register_action_handler("fail", |_| async move {
+        ActionResponse::failed(35001, "something went wrong")
+    });
+    let (handle, url) = start_http(&mut onebot).await;
+
+    let response = post(
+        &url,
+        json!({"action": "echo", "params": {"message": "hello"}, "echo": {"seq": 1}}),
+    )
+    .await;
+    assert_eq!(
+        response,
+        json!({
+            "status": "ok",
+            "retcode": 0,
+            "data": "hello",
+            "message": "",
+            "echo": {"seq": 1},
+        })
+    );
+
+    let response = post(&url, json!({"action": "fail", "params": {}})).await;
+    assert_eq!(
+        response,
+        json!({
+            "status": "failed",
+            "retcode": 35001,
+            "data": null,
+            "message": "something went wrong",
+        })
+    );
+
+    handle.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn http_answers_bad_requests_with_retcodes() {
+    let mut onebot = test_onebot();
+    onebot.register_action_handler("panic", |_| async move {
+        panic!("handler bug");
+    });
+    let (handle, url) = start_http(&mut onebot).await;
+
+    let client = reqwest::Client::new();
+    let text = client
+        .post(&url)
+        .body("not json")
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    let response: Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(response["status"], "failed");
+    assert_eq!(response["retcode"], 10001);
+
+    let response = post(&url, json!({"params": {}, "echo": 7})).await;
+    assert_eq!(response["retcode"], 10001);
+    assert_eq!(response["echo"], 7);
+
+    let response = post(&url, json!({"action": "missing", "params": {}})).await;
+    assert_eq!(response["retcode"], 10002);
+
+    let response = post(&url, json!({"action": "panic", "params": []})).await;
+    assert_eq!(response["retcode"], 10003);
+
+    let response = post(&url, json!({"action": "panic", "params": {}})).await;
+    assert_eq!(response["retcode"], 20002);
+
+    let response = post(&url, json!({"action": "missing", "params": {}})).await;
+    assert_eq!(response["retcode"], 10002);
+
+    handle.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn builtin_meta_actions_answer_and_can_be_overridden() {
+    let mut onebot = test_onebot();
+    onebot.set_impl("test_onebot", "1.2.3");
+    onebot.register_action_handler(
+        "echo",
+        |params: Value| async move { ActionResponse::ok(params) },
+    );
+    onebot.register_action_handler("get_status", |_| async move {
+        ActionResponse::ok(json!({"good": false, "online": false}))
+    });
+    let (handle, url) = start_http(&mut onebot).await;
+
+    let response = post(&url, json!({"action": "get_supported_actions"})).await;
+    assert_eq!(
+        response["data"],
+        json!(["echo", "get_status", "get_supported_actions", "get_version"])
+    );
+
+    let response = post(&url, json!({"action": "get_version"})).await;
+    assert_eq!(
+        response["data"],
+        json!({
+            "impl": "test_onebot",
+            "platform": "test",
+            "version": "1.2.3",
+            "onebot_version": "12",
+        })
+    );
+
+    let response = post(&url, json!({"action": "get_status"})).await;
+    assert_eq!(response["data"], json!({"good": false, "online": false}));
+
+    handle.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn builtin_get_status_reports_online_flag() {
+    let mut onebot = test_onebot();
+    let (handle, url) = start_http(&mut onebot).await;
+
+    let response = post(&url, json!({"action": "get_status"})).await;
+    assert_eq!(response["data"], json!({"good": true, "online": true}));
+
+    onebot.set_online(false);
+    let response = post(&url, json!({"action": "get_status"})).await;
+    assert_eq!(response["data"], json!({"good": true, "online": false}));
+
+    handle.shutdown().await;
+}
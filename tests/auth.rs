@@ -0,0 +1,180 @@
+mod common;
+
+use common::{start_http, start_ws, test_onebot};
+use libonebot::{comm::WebSocketReverse, OneBotHandle};
+use serde_json::json;
+use std::time::Duration;
+use tokio::{net::TcpListener, sync::oneshot};
+use tokio_tungstenite::tungstenite::{
+    client::IntoClientRequest,
+    handshake::server::{Request, Response},
+    Error as WsError,
+};
+
+async fn start_http_with_token(access_token: Option<&str>) -> (OneBotHandle, String) {
+    let mut onebot = test_onebot();
+    if let Some(access_token) = access_token {
+        onebot.set_access_token(access_token);
+    }
+    start_http(&mut onebot).await
+}
+
+fn get_status() -> String {
+    json!({"action": "get_status", "params": {}}).to_string()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn http_rejects_missing_or_wrong_access_token() {
+    let (handle, url) = start_http_with_token(Some("secret")).await;
+    let client = reqwest::Client::new();
+
+    let response = client.post(&url).body(get_status()).send().await.unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = client
+        .post(&url)
+        .header("Authorization", "Bearer wrong")
+        .body(get_status())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = client
+        .post(format!("{}/?access_token=wrong", url))
+        .body(get_status())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 403);
+
+    handle.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn http_accepts_bearer_header_and_query_token() {
+    let (handle, url) = start_http_with_token(Some("secret")).await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(&url)
+        .header("Authorization", "Bearer secret")
+        .body(get_status())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.text().await.unwrap().contains("\"ok\""));
+
+    // 认证方案名称不区分大小写
+    let response = client
+        .post(&url)
+        .header("Authorization", "bearer secret")
+        .body(get_status())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = client
+        .post(format!("{}/?access_token=secret", url))
+        .body(get_status())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+
+    handle.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn http_without_access_token_accepts_any_client() {
+    let (handle, url) = start_http_with_token(None).await;
+
+    let response = reqwest::Client::new()
+        .post(&url)
+        .body(get_status())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+
+    handle.shutdown().await;
+}
+
+fn handshake_status(result: Result<impl Sized, WsError>) -> u16 {
+    match result {
+        Ok(_) => 101,
+        Err(WsError::Http(response)) => response.status().as_u16(),
+        Err(err) => panic!("unexpected handshake error: {}", err),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn ws_handshake_checks_access_token() {
+    let mut onebot = test_onebot();
+    onebot.set_access_token("secret");
+    let (handle, url) = start_ws(&mut onebot, |ws| ws).await;
+
+    let result = tokio_tungstenite::connect_async(url.as_str()).await;
+    assert_eq!(handshake_status(result), 401);
+
+    let mut request = url.as_str().into_client_request().unwrap();
+    request
+        .headers_mut()
+        .insert("Authorization", "Bearer wrong".parse().unwrap());
+    let result = tokio_tungstenite::connect_async(request).await;
+    assert_eq!(handshake_status(result), 403);
+
+    let mut request = url.as_str().into_client_request().unwrap();
+    request
+        .headers_mut()
+        .insert("Authorization", "Bearer secret".parse().unwrap());
+    let result = tokio_tungstenite::connect_async(request).await;
+    assert_eq!(handshake_status(result), 101);
+
+    let result = tokio_tungstenite::connect_async(format!("{}/?access_token=secret", url)).await;
+    assert_eq!(handshake_status(result), 101);
+
+    handle.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn ws_reverse_sends_onebot_headers() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (headers_sender, headers_receiver) = oneshot::channel();
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        #[allow(clippy::result_large_err)]
+        let callback = |request: &Request, response: Response| {
+            let _ = headers_sender.send(request.headers().clone());
+            Ok(response)
+        };
+        let _ws_stream = tokio_tungstenite::accept_hdr_async(stream, callback)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    });
+
+    let mut onebot = test_onebot();
+    onebot.set_access_token("secret").add_comm(
+        &"ws_reverse",
+        WebSocketReverse::new(format!("ws://127.0.0.1:{}", port)),
+    );
+    onebot.set_impl("test-impl", "1.2.3");
+    let handle = onebot.run().await.unwrap();
+
+    let headers = tokio::time::timeout(Duration::from_secs(2), headers_receiver)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(headers["authorization"], "Bearer secret");
+    assert_eq!(headers["user-agent"], "OneBot/12 (test) test-impl/1.2.3");
+    assert_eq!(headers["x-onebot-version"], "12");
+    assert_eq!(headers["x-impl"], "test-impl");
+    assert_eq!(headers["x-platform"], "test");
+    assert_eq!(headers["x-self-id"], "bot");
+
+    handle.shutdown().await;
+}
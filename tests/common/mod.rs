@@ -0,0 +1,104 @@
+// 各集成测试共用的辅助函数，并非每个测试文件都会用到全部内容
+#![allow(dead_code)]
+
+use async_trait::async_trait;
+use libonebot::{
+    comm::{WebSocket, HTTP},
+    config::{Config, LogOutput},
+    Comm, CommContext, Event, OneBot, OneBotHandle, Result,
+};
+use std::{net::TcpListener, time::Duration};
+use tokio::sync::mpsc;
+
+pub fn quiet_config() -> Config {
+    let mut config = Config::new();
+    config.log.output = LogOutput::Nul;
+    config.log.path = None;
+    config
+}
+
+pub fn test_onebot() -> OneBot {
+    let mut onebot = OneBot::new("test");
+    onebot.set_self_id("bot").set_config(quiet_config());
+    onebot
+}
+
+// 绑定系统分配的端口并将监听套接字直接交给通信方式，
+// 避免释放端口后再次绑定时与并行运行的其他测试争抢同一端口
+pub fn local_listener() -> (TcpListener, u16) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    (listener, port)
+}
+
+// 添加监听系统分配端口的 HTTP 通信方式并运行，返回 HTTP 地址
+pub async fn start_http(onebot: &mut OneBot) -> (OneBotHandle, String) {
+    let (listener, port) = local_listener();
+    onebot.add_comm(&"http", HTTP::from_listener(listener).unwrap());
+    let handle = onebot.run().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    (handle, format!("http://127.0.0.1:{}", port))
+}
+
+// 添加监听系统分配端口的 WebSocket 通信方式并运行，返回 WebSocket 地址；ws 用于调整通信方式的配置
+pub async fn start_ws(
+    onebot: &mut OneBot,
+    ws: impl FnOnce(WebSocket) -> WebSocket,
+) -> (OneBotHandle, String) {
+    let (listener, port) = local_listener();
+    onebot.add_comm(&"ws", ws(WebSocket::from_listener(listener).unwrap()));
+    let handle = onebot.run().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    (handle, format!("ws://127.0.0.1:{}", port))
+}
+
+// 取出已经收到的全部消息
+pub fn drain<T>(received: &mut mpsc::UnboundedReceiver<T>) -> Vec<T> {
+    let mut items = Vec::new();
+    while let Ok(item) = received.try_recv() {
+        items.push(item);
+    }
+    items
+}
+
+// 将收到的事件转发给测试；订阅后先等待 delay 再开始读取，用于模拟处理缓慢的通信方式
+#[derive(Debug, Clone)]
+pub struct Collector {
+    delay: Duration,
+    events: mpsc::UnboundedSender<Event>,
+}
+
+impl Collector {
+    pub fn new(events: mpsc::UnboundedSender<Event>) -> Self {
+        Self {
+            delay: Duration::ZERO,
+            events,
+        }
+    }
+
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+}
+
+#[async_trait]
+impl Comm for Collector {
+    async fn start(&self, context: CommContext) -> Result<()> {
+        let mut event_receiver = context.event_bus.subscribe();
+        tokio::select! {
+            _ = context.shutdown.cancelled() => return Ok(()),
+            _ = tokio::time::sleep(self.delay) => {}
+        }
+        loop {
+            tokio::select! {
+                _ = context.shutdown.cancelled() => return Ok(()),
+                event = event_receiver.recv() => {
+                    if self.events.send(event.into_event()).is_err() {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,230 @@
+use chrono::{TimeZone, Utc};
+use libonebot::{
+    event::{MemberDecreaseType, MemberIncreaseType, MessageDeleteType},
+    Event, Group, Message, Meta, Notice, Request, Status, User,
+};
+use serde_json::{json, Value};
+
+fn notice_json(notice: Notice) -> Value {
+    let event = Event::build("1")
+        .platform("test")
+        .time(Utc.timestamp_opt(1632847927, 0).unwrap())
+        .bot_user(User::new("bot"))
+        .notice(notice);
+    serde_json::to_value(&event).unwrap()
+}
+
+#[test]
+fn private_notices() {
+    assert_eq!(
+        notice_json(Notice::friend_increase("10001")),
+        json!({
+            "id": "1",
+            "platform": "test",
+            "time": 1632847927,
+            "self_id": "bot",
+            "type": "notice",
+            "detail_type": "friend_increase",
+            "sub_type": "",
+            "user_id": "10001",
+        })
+    );
+
+    let json = notice_json(Notice::private_message_delete("6283", "10001"));
+    assert_eq!(json["detail_type"], "private_message_delete");
+    assert_eq!(json["message_id"], "6283");
+    assert_eq!(json["user_id"], "10001");
+}
+
+#[test]
+fn group_notices_carry_sub_types() {
+    let json = notice_json(Notice::group_member_increase(
+        MemberIncreaseType::Invite,
+        "20001",
+        "10001",
+        "10002",
+    ));
+    assert_eq!(json["detail_type"], "group_member_increase");
+    assert_eq!(json["sub_type"], "invite");
+    assert_eq!(json["group_id"], "20001");
+    assert_eq!(json["user_id"], "10001");
+    assert_eq!(json["operator_id"], "10002");
+
+    let json = notice_json(Notice::group_member_decrease(
+        MemberDecreaseType::Kick,
+        "20001",
+        "10001",
+        "10002",
+    ));
+    assert_eq!(json["detail_type"], "group_member_decrease");
+    assert_eq!(json["sub_type"], "kick");
+
+    let json = notice_json(Notice::group_message_delete(
+        MessageDeleteType::Recall,
+        "20001",
+        "6283",
+        "10001",
+        "10001",
+    ));
+    assert_eq!(json["detail_type"], "group_message_delete");
+    assert_eq!(json["sub_type"], "recall");
+    assert_eq!(json["message_id"], "6283");
+}
+
+#[test]
+fn guild_and_channel_notices() {
+    let json = notice_json(Notice::channel_message_delete(
+        MessageDeleteType::Delete,
+        "30001",
+        "40001",
+        "6283",
+        "10001",
+        "10002",
+    ));
+    assert_eq!(json["detail_type"], "channel_message_delete");
+    assert_eq!(json["sub_type"], "delete");
+    assert_eq!(json["guild_id"], "30001");
+    assert_eq!(json["channel_id"], "40001");
+
+    let json = notice_json(Notice::channel_create("30001", "40001", "10002"));
+    assert_eq!(json["detail_type"], "channel_create");
+    assert_eq!(json["sub_type"], "");
+    assert_eq!(json["operator_id"], "10002");
+    assert!(json.get("user_id").is_none());
+
+    let json = notice_json(Notice::guild_member_increase(
+        MemberIncreaseType::Join,
+        "30001",
+        "10001",
+        "10001",
+    ));
+    assert_eq!(json["detail_type"], "guild_member_increase");
+    assert_eq!(json["sub_type"], "join");
+    assert!(json.get("channel_id").is_none());
+}
+
+#[test]
+fn request_events_carry_flags() {
+    let event = Event::build("2")
+        .platform("test")
+        .time(Utc.timestamp_opt(1632847927, 0).unwrap())
+        .bot_user(User::new("bot"))
+        .friend_request("flag-1", "10001", "hello");
+    assert_eq!(
+        serde_json::to_value(&event).unwrap(),
+        json!({
+            "id": "2",
+            "platform": "test",
+            "time": 1632847927,
+            "self_id": "bot",
+            "type": "request",
+            "detail_type": "friend",
+            "sub_type": "",
+            "user_id": "10001",
+            "flag": "flag-1",
+            "comment": "hello",
+        })
+    );
+
+    let json =
+        serde_json::to_value(Event::build("3").group_join_request("flag-2", "20001", "10001", ""))
+            .unwrap();
+    assert_eq!(json["detail_type"], "group");
+    assert_eq!(json["sub_type"], "join");
+    assert_eq!(json["group_id"], "20001");
+    assert_eq!(json["flag"], "flag-2");
+
+    let request = Request::group_invite("flag-3", "20001", "10002", "");
+    assert_eq!(request.flag(), "flag-3");
+    let json = serde_json::to_value(Event::build("4").request(request)).unwrap();
+    assert_eq!(json["sub_type"], "invite");
+    assert_eq!(json["user_id"], "10002");
+}
+
+#[test]
+fn message_events_carry_sub_type_alt_message_and_extended_fields() {
+    let message = Message::build("6283")
+        .sub_type("normal")
+        .mention("10002")
+        .text(" look at this ")
+        .image("e30f9684")
+        .group(
+            Group::new("20001").extended("group_level", 3),
+            User::new("10001").extended("title", "admin"),
+        );
+    let event = Event::build("5")
+        .platform("qq")
+        .time(Utc.timestamp_opt(1632847927, 0).unwrap())
+        .bot_user(User::new("bot"))
+        .message(message);
+
+    assert_eq!(
+        serde_json::to_value(&event).unwrap(),
+        json!({
+            "id": "5",
+            "platform": "qq",
+            "time": 1632847927,
+            "self_id": "bot",
+            "type": "message",
+            "detail_type": "group",
+            "sub_type": "normal",
+            "message": [
+                {"type": "mention", "data": {"user_id": "10002"}},
+                {"type": "text", "data": {"text": " look at this "}},
+                {"type": "image", "data": {"file_id": "e30f9684"}},
+            ],
+            "message_id": "6283",
+            "alt_message": "@10002 look at this [图片]",
+            "user_id": "10001",
+            "group_id": "20001",
+            "qq.title": "admin",
+            "qq.group_level": "3",
+        })
+    );
+}
+
+#[test]
+fn meta_events_flatten_extended_fields() {
+    let status = Status {
+        good: true,
+        online: true,
+    };
+    let event = Event::build("6")
+        .platform("qq")
+        .meta(Meta::heartbeat(5000, status).extended("uptime", 42));
+    let json = serde_json::to_value(&event).unwrap();
+    assert_eq!(json["detail_type"], "heartbeat");
+    assert_eq!(json["interval"], 5000);
+    assert_eq!(json["status"], json!({"good": true, "online": true}));
+    assert_eq!(json["qq.uptime"], "42");
+}
+
+#[test]
+fn sender_extended_fields_take_precedence_over_group() {
+    let message = Message::build("6283").text("hi").group(
+        Group::new("20001")
+            .extended("k", "group")
+            .extended("level", 3),
+        User::new("10001").extended("k", "user"),
+    );
+    let event = Event::build("7").platform("qq").message(message);
+    let json = serde_json::to_value(&event).unwrap();
+    assert_eq!(json["qq.k"], "user");
+    assert_eq!(json["qq.level"], "3");
+}
+
+#[test]
+fn extended_fields_require_platform() {
+    let status = Status {
+        good: true,
+        online: true,
+    };
+    let event = Event::build("8").meta(Meta::heartbeat(5000, status).extended("uptime", 42));
+    let json = serde_json::to_value(&event).unwrap();
+    assert_eq!(json["platform"], "");
+    assert!(json
+        .as_object()
+        .unwrap()
+        .keys()
+        .all(|key| !key.contains('.')));
+}
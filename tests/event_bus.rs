@@ -0,0 +1,167 @@
+mod common;
+
+use common::{local_listener, quiet_config, Collector};
+use futures_util::StreamExt;
+use libonebot::{
+    comm::WebSocket,
+    config::{Config, DefaultConfigFile, EventQueue, OverflowPolicy},
+    Event, EventSink, EventStats, Notice, OneBot, SharedEvent, User,
+};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+// 生成 count 个事件，id 依次为 0..count；返回收到的事件 id 以及事件统计
+async fn burst(
+    count: usize,
+    delay: Duration,
+    event_queue: Option<EventQueue>,
+) -> (Vec<String>, EventStats) {
+    let (events, mut received) = mpsc::unbounded_channel();
+    let collector = Collector::new(events).delay(delay);
+
+    let mut onebot = OneBot::new("test");
+    onebot.set_self_id("bot").set_config(quiet_config());
+    if let Some(event_queue) = event_queue {
+        onebot.add_comm_with_event_queue(&"collector", collector, event_queue);
+    } else {
+        onebot.add_comm(&"collector", collector);
+    }
+    onebot.register_event_generator(move |sink: EventSink| async move {
+        // 等待通信方式完成订阅
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        for i in 0..count {
+            let event = Event::build(i)
+                .bot_user(User::new("bot"))
+                .notice(Notice::friend_increase("10001"));
+            sink.send(event).await;
+        }
+        Ok(())
+    });
+
+    let handle = onebot.run().await.unwrap();
+    tokio::time::sleep(delay + Duration::from_millis(300)).await;
+    handle.shutdown().await;
+
+    let mut ids = Vec::new();
+    while let Ok(event) = received.try_recv() {
+        ids.push(event.id);
+    }
+    (ids, onebot.event_stats())
+}
+
+fn ids(range: std::ops::Range<usize>) -> Vec<String> {
+    range.map(|i| i.to_string()).collect()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn default_queue_absorbs_bursts() {
+    let (received, stats) = burst(200, Duration::ZERO, None).await;
+    assert_eq!(received, ids(0..200));
+    assert_eq!(stats.published, 200);
+    assert_eq!(stats.dropped, 0);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn drop_newest_keeps_queued_events() {
+    let queue = EventQueue::new(5, OverflowPolicy::DropNewest);
+    let (received, stats) = burst(20, Duration::from_millis(300), Some(queue)).await;
+    assert_eq!(received, ids(0..5));
+    assert_eq!(stats.dropped, 15);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn drop_oldest_keeps_latest_events() {
+    let queue = EventQueue::new(5, OverflowPolicy::DropOldest);
+    let (received, stats) = burst(20, Duration::from_millis(300), Some(queue)).await;
+    assert_eq!(received, ids(15..20));
+    assert_eq!(stats.dropped, 15);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn block_waits_for_slow_subscribers() {
+    let queue = EventQueue::new(2, OverflowPolicy::Block);
+    let (received, stats) = burst(20, Duration::from_millis(300), Some(queue)).await;
+    assert_eq!(received, ids(0..20));
+    assert_eq!(stats.dropped, 0);
+}
+
+#[test]
+fn event_queue_from_config_file() {
+    let config_file: DefaultConfigFile = serde_json::from_value(serde_json::json!({
+        "event_queue": {"capacity": 64, "overflow": "block"},
+    }))
+    .unwrap();
+    let config = Config::from_config_file(&config_file).unwrap();
+    assert_eq!(
+        config.event_queue,
+        EventQueue::new(64, OverflowPolicy::Block)
+    );
+
+    let config_file: DefaultConfigFile = serde_json::from_value(serde_json::json!({
+        "event_queue": {"overflow": "unknown"},
+    }))
+    .unwrap();
+    assert!(Config::from_config_file(&config_file).is_err());
+}
+
+#[test]
+fn shared_event_serializes_once() {
+    let event = SharedEvent::new(
+        Event::build("1")
+            .platform("test")
+            .bot_user(User::new("bot"))
+            .notice(Notice::friend_increase("10001")),
+    );
+    let first = event.json().unwrap();
+    let second = event.clone().json().unwrap();
+    assert_eq!(first.as_ptr(), second.as_ptr());
+
+    assert_eq!(event.json_string().unwrap().as_bytes(), &first[..]);
+
+    let json: serde_json::Value = serde_json::from_slice(&first).unwrap();
+    assert_eq!(json["platform"], "test");
+    assert_eq!(json["self_id"], "bot");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn generator_events_fan_out_to_all_clients() {
+    let (listener, port) = local_listener();
+    let mut onebot = OneBot::new("test");
+    onebot
+        .set_self_id("bot")
+        .set_config(quiet_config())
+        .add_comm(&"ws", WebSocket::from_listener(listener).unwrap());
+    onebot.register_event_generator(|sink: EventSink| async move {
+        // 等待客户端连接
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let event = Event::build("1")
+            .bot_user(User::new("bot"))
+            .notice(Notice::friend_increase("10001"));
+        sink.send(event).await;
+        Ok(())
+    });
+    let handle = onebot.run().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let url = format!("ws://127.0.0.1:{}", port);
+    let mut clients = Vec::new();
+    for _ in 0..3 {
+        let (client, _) = tokio_tungstenite::connect_async(url.as_str())
+            .await
+            .unwrap();
+        clients.push(client);
+    }
+    for client in clients.iter_mut() {
+        let msg = tokio::time::timeout(Duration::from_secs(1), client.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_str(&msg.into_text().unwrap()).unwrap();
+        // 平台名称由 OneBot 在发布前填入
+        assert_eq!(json["platform"], "test");
+        assert_eq!(json["detail_type"], "friend_increase");
+    }
+
+    handle.shutdown().await;
+}
@@ -0,0 +1,113 @@
+mod common;
+
+use common::{quiet_config, Collector};
+use libonebot::{Event, Notice, OneBot, User};
+use serde_json::Value;
+use std::{collections::HashSet, time::Duration};
+use tokio::sync::mpsc;
+
+fn friend_increase(id: &str) -> Event {
+    Event::build(id).notice(Notice::friend_increase("10001"))
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn event_sink_fills_missing_platform_bot_user_and_id() {
+    let (events, mut received) = mpsc::unbounded_channel();
+    let mut onebot = OneBot::new("test");
+    onebot
+        .set_self_id("bot")
+        .set_config(quiet_config())
+        .add_comm(&"collector", Collector::new(events));
+    // 可以在运行之前获取，并克隆到多个任务中
+    let sink = onebot.event_sink();
+
+    let handle = onebot.run().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let mut tasks = Vec::new();
+    for _ in 0..3 {
+        let sink = sink.clone();
+        tasks.push(tokio::spawn(async move {
+            for _ in 0..5 {
+                sink.send(friend_increase("")).await;
+            }
+        }));
+    }
+    for task in tasks {
+        task.await.unwrap();
+    }
+    // 调用方显式设置的字段保持不变
+    sink.send(
+        friend_increase("custom")
+            .platform("other-platform")
+            .bot_user(User::new("other")),
+    )
+    .await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    handle.shutdown().await;
+
+    let mut ids = HashSet::new();
+    while let Ok(event) = received.try_recv() {
+        let json: Value = serde_json::to_value(&event).unwrap();
+        if event.id == "custom" {
+            assert_eq!(json["platform"], "other-platform");
+            assert_eq!(json["self_id"], "other");
+        } else {
+            assert_eq!(json["platform"], "test");
+            assert_eq!(json["self_id"], "bot");
+        }
+        assert!(!event.id.is_empty());
+        ids.insert(event.id);
+    }
+    assert_eq!(ids.len(), 16);
+    assert!(ids.contains("custom"));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn event_sink_after_shutdown_is_harmless() {
+    let mut onebot = OneBot::new("test");
+    onebot.set_self_id("bot").set_config(quiet_config());
+    let sink = onebot.event_sink();
+    let handle = onebot.run().await.unwrap();
+    handle.shutdown().await;
+
+    tokio::time::timeout(Duration::from_secs(1), sink.send(friend_increase("")))
+        .await
+        .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn builder_events_and_heartbeats_get_generated_ids() {
+    let (events, mut received) = mpsc::unbounded_channel();
+    let mut config = quiet_config();
+    config.heartbeat = Some(50);
+    let mut onebot = OneBot::new("test");
+    onebot
+        .set_self_id("bot")
+        .set_config(config)
+        .add_comm(&"collector", Collector::new(events));
+    let sink = onebot.event_sink();
+
+    let handle = onebot.run().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    sink.send(Event::builder().notice(Notice::friend_increase("10001")))
+        .await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    handle.shutdown().await;
+
+    let mut ids = HashSet::new();
+    let mut heartbeats = 0;
+    let mut notices = 0;
+    while let Ok(event) = received.try_recv() {
+        let json: Value = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["self_id"], "bot");
+        match json["detail_type"].as_str().unwrap() {
+            "heartbeat" => heartbeats += 1,
+            "friend_increase" => notices += 1,
+            detail_type => panic!("unexpected event {}", detail_type),
+        }
+        assert!(ids.insert(event.id));
+    }
+    assert!(heartbeats >= 2, "{}", heartbeats);
+    assert_eq!(notices, 1);
+}
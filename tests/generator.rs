@@ -0,0 +1,123 @@
+mod common;
+
+use common::{drain, test_onebot, Collector};
+use futures_util::StreamExt;
+use libonebot::{
+    config::{EventQueue, OverflowPolicy},
+    Event, EventSink, Notice, OneBot, User,
+};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+fn notice(id: usize) -> Event {
+    Event::build(id)
+        .bot_user(User::new("bot"))
+        .notice(Notice::friend_increase("10001"))
+}
+
+fn new_onebot(events: mpsc::UnboundedSender<Event>) -> OneBot {
+    let mut onebot = test_onebot();
+    onebot.add_comm(&"collector", Collector::new(events));
+    onebot
+}
+
+fn event_ids(received: &mut mpsc::UnboundedReceiver<Event>) -> Vec<String> {
+    drain(received)
+        .into_iter()
+        .map(|event| {
+            assert_eq!(event.platform, "test");
+            event.id
+        })
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn async_generator_pushes_events() {
+    let (events, mut received) = mpsc::unbounded_channel();
+    let mut onebot = new_onebot(events);
+    onebot.register_event_generator(|sink: EventSink| async move {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        for i in 0..3 {
+            sink.send(notice(i)).await;
+        }
+        Ok(())
+    });
+
+    let handle = onebot.run().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    handle.shutdown().await;
+
+    assert_eq!(event_ids(&mut received), vec!["0", "1", "2"]);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn event_stream_is_forwarded() {
+    let (events, mut received) = mpsc::unbounded_channel();
+    let mut onebot = new_onebot(events);
+    // 延迟产生首个事件，等待通信方式完成订阅
+    let stream = futures::stream::iter(0..3).then(|i| async move {
+        if i == 0 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        notice(i)
+    });
+    onebot.register_event_stream(stream);
+
+    let handle = onebot.run().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    handle.shutdown().await;
+
+    assert_eq!(event_ids(&mut received), vec!["0", "1", "2"]);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn running_without_generator_is_a_no_op() {
+    let (events, mut received) = mpsc::unbounded_channel();
+    let mut onebot = new_onebot(events);
+
+    let handle = onebot.run().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(onebot.status().good);
+    tokio::time::timeout(Duration::from_secs(1), handle.shutdown())
+        .await
+        .unwrap();
+
+    assert!(event_ids(&mut received).is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn block_policy_applies_backpressure_to_generator() {
+    let (events, mut received) = mpsc::unbounded_channel();
+    let mut onebot = test_onebot();
+    onebot.add_comm_with_event_queue(
+        &"collector",
+        Collector::new(events).delay(Duration::from_millis(300)),
+        EventQueue::new(1, OverflowPolicy::Block),
+    );
+    let (done, mut finished) = mpsc::unbounded_channel();
+    onebot.register_event_generator(move |sink: EventSink| {
+        let done = done.clone();
+        async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            let start = Instant::now();
+            for i in 0..5 {
+                sink.send(notice(i)).await;
+            }
+            let _ = done.send(start.elapsed());
+            Ok(())
+        }
+    });
+
+    let handle = onebot.run().await.unwrap();
+    let elapsed = tokio::time::timeout(Duration::from_secs(2), finished.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    // 等待订阅者读取最后一个事件
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    handle.shutdown().await;
+
+    // 订阅者开始读取之前，生成器只能放入一个事件
+    assert!(elapsed >= Duration::from_millis(200), "{:?}", elapsed);
+    assert_eq!(event_ids(&mut received), vec!["0", "1", "2", "3", "4"]);
+}
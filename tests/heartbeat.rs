@@ -0,0 +1,61 @@
+mod common;
+
+use common::{quiet_config, Collector};
+use libonebot::{event::MetaDetail, EventContent, OneBot};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn heartbeat_emits_meta_events_until_shutdown() {
+    let (events, mut received) = mpsc::unbounded_channel();
+
+    let mut onebot = OneBot::new("test");
+    onebot
+        .set_self_id("bot")
+        .set_config(quiet_config())
+        .enable_heartbeat(50)
+        .add_comm(&"collector", Collector::new(events));
+    onebot.set_online(false);
+
+    let handle = onebot.run().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let mut heartbeats = 0;
+    while let Ok(event) = received.try_recv() {
+        assert_eq!(event.platform, "test");
+        match event.content {
+            EventContent::Meta(meta) => match meta.detail {
+                MetaDetail::Heartbeat { interval, status } => {
+                    assert_eq!(interval, 50);
+                    assert!(status.good);
+                    assert!(!status.online);
+                    heartbeats += 1;
+                }
+            },
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+    assert!(heartbeats >= 3, "only {} heartbeats received", heartbeats);
+
+    handle.shutdown().await;
+    while received.try_recv().is_ok() {}
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(received.try_recv().is_err());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn heartbeat_is_disabled_by_default() {
+    let (events, mut received) = mpsc::unbounded_channel();
+
+    let mut onebot = OneBot::new("test");
+    onebot
+        .set_self_id("bot")
+        .set_config(quiet_config())
+        .add_comm(&"collector", Collector::new(events));
+
+    let handle = onebot.run().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    handle.shutdown().await;
+
+    assert!(received.try_recv().is_err());
+}
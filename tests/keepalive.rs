@@ -0,0 +1,86 @@
+mod common;
+
+use common::{start_ws, test_onebot};
+use futures_util::{SinkExt, StreamExt};
+use libonebot::OneBotHandle;
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::Message;
+
+async fn start_keepalive_ws() -> (OneBotHandle, String) {
+    let mut onebot = test_onebot();
+    start_ws(&mut onebot, |ws| {
+        ws.keepalive(Duration::from_millis(50), Duration::from_millis(50))
+    })
+    .await
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn ws_pings_responsive_clients_and_keeps_them_connected() {
+    let (handle, url) = start_keepalive_ws().await;
+    let (mut ws_stream, _) = tokio_tungstenite::connect_async(url.as_str())
+        .await
+        .unwrap();
+
+    // 客户端持续读取时会自动回复 Pong
+    let mut pings = 0;
+    let deadline = tokio::time::sleep(Duration::from_millis(400));
+    tokio::pin!(deadline);
+    loop {
+        tokio::select! {
+            _ = &mut deadline => break,
+            msg = ws_stream.next() => match msg {
+                Some(Ok(Message::Ping(_))) => pings += 1,
+                other => panic!("unexpected message: {:?}", other),
+            },
+        }
+    }
+    assert!(pings >= 3);
+
+    handle.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn ws_drops_clients_that_stop_answering_pings() {
+    let (handle, url) = start_keepalive_ws().await;
+    let (mut ws_stream, _) = tokio_tungstenite::connect_async(url.as_str())
+        .await
+        .unwrap();
+
+    // 不读取就不会回复 Pong，服务端应当在 Pong 超时后断开连接
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let closed = tokio::time::timeout(Duration::from_secs(1), async {
+        while let Some(Ok(Message::Ping(_))) = ws_stream.next().await {}
+    })
+    .await;
+    assert!(closed.is_ok());
+
+    handle.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn ws_answers_pings_and_close_frames() {
+    let mut onebot = test_onebot();
+    let (handle, url) = start_ws(&mut onebot, |ws| ws.disable_keepalive()).await;
+    let (mut ws_stream, _) = tokio_tungstenite::connect_async(url.as_str())
+        .await
+        .unwrap();
+
+    ws_stream
+        .send(Message::Ping(b"ping".to_vec()))
+        .await
+        .unwrap();
+    let msg = tokio::time::timeout(Duration::from_secs(1), ws_stream.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert_eq!(msg, Message::Pong(b"ping".to_vec()));
+
+    ws_stream.send(Message::Close(None)).await.unwrap();
+    let msg = tokio::time::timeout(Duration::from_secs(1), ws_stream.next())
+        .await
+        .unwrap();
+    assert!(matches!(msg, Some(Ok(Message::Close(_)))));
+
+    handle.shutdown().await;
+}
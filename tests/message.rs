@@ -0,0 +1,208 @@
+use libonebot::{
+    message::{deserialize_segments, segments_from_value},
+    Error, Message, MessageSegment, User,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+fn assert_round_trip(segment: MessageSegment, expected: Value) {
+    assert_eq!(serde_json::to_value(&segment).unwrap(), expected);
+    assert_eq!(
+        serde_json::from_value::<MessageSegment>(expected).unwrap(),
+        segment
+    );
+}
+
+#[test]
+fn segments_use_type_and_data() {
+    assert_round_trip(
+        MessageSegment::Text {
+            text: "hello".to_string(),
+        },
+        json!({"type": "text", "data": {"text": "hello"}}),
+    );
+    assert_round_trip(
+        MessageSegment::Mention {
+            user_id: "10001".to_string(),
+        },
+        json!({"type": "mention", "data": {"user_id": "10001"}}),
+    );
+    assert_round_trip(
+        MessageSegment::MentionAll {},
+        json!({"type": "mention_all", "data": {}}),
+    );
+    assert_round_trip(
+        MessageSegment::Image {
+            file_id: "e30f9684".to_string(),
+        },
+        json!({"type": "image", "data": {"file_id": "e30f9684"}}),
+    );
+    assert_round_trip(
+        MessageSegment::Voice {
+            file_id: "e30f9684".to_string(),
+        },
+        json!({"type": "voice", "data": {"file_id": "e30f9684"}}),
+    );
+    assert_round_trip(
+        MessageSegment::Audio {
+            file_id: "e30f9684".to_string(),
+        },
+        json!({"type": "audio", "data": {"file_id": "e30f9684"}}),
+    );
+    assert_round_trip(
+        MessageSegment::Video {
+            file_id: "e30f9684".to_string(),
+        },
+        json!({"type": "video", "data": {"file_id": "e30f9684"}}),
+    );
+    assert_round_trip(
+        MessageSegment::File {
+            file_id: "e30f9684".to_string(),
+        },
+        json!({"type": "file", "data": {"file_id": "e30f9684"}}),
+    );
+    assert_round_trip(
+        MessageSegment::Location {
+            latitude: 31.032315,
+            longitude: 121.447127,
+            title: "上海交通大学闵行校区".to_string(),
+            content: "中国上海市闵行区东川路800号".to_string(),
+        },
+        json!({
+            "type": "location",
+            "data": {
+                "latitude": 31.032315,
+                "longitude": 121.447127,
+                "title": "上海交通大学闵行校区",
+                "content": "中国上海市闵行区东川路800号",
+            },
+        }),
+    );
+    assert_round_trip(
+        MessageSegment::Reply {
+            message_id: "6283".to_string(),
+            user_id: Some("10001".to_string()),
+        },
+        json!({"type": "reply", "data": {"message_id": "6283", "user_id": "10001"}}),
+    );
+    assert_round_trip(
+        MessageSegment::Reply {
+            message_id: "6283".to_string(),
+            user_id: None,
+        },
+        json!({"type": "reply", "data": {"message_id": "6283"}}),
+    );
+}
+
+#[test]
+fn missing_data_is_an_empty_object() {
+    assert_eq!(
+        serde_json::from_value::<MessageSegment>(json!({"type": "mention_all"})).unwrap(),
+        MessageSegment::MentionAll {}
+    );
+    let segment = serde_json::from_value::<MessageSegment>(json!({"type": "qq.shake"})).unwrap();
+    assert_eq!(
+        serde_json::to_value(&segment).unwrap(),
+        json!({"type": "qq.shake", "data": {}})
+    );
+    assert!(serde_json::from_value::<MessageSegment>(json!({"type": "text"})).is_err());
+}
+
+#[test]
+fn message_builder_produces_v12_content() {
+    let message = Message::build("1")
+        .reply("0", "10001")
+        .mention("10001")
+        .text(" hi")
+        .image("e30f9684")
+        .private(User::new("10001"));
+
+    assert_eq!(
+        serde_json::to_value(&message.content).unwrap(),
+        json!([
+            {"type": "reply", "data": {"message_id": "0", "user_id": "10001"}},
+            {"type": "mention", "data": {"user_id": "10001"}},
+            {"type": "text", "data": {"text": " hi"}},
+            {"type": "image", "data": {"file_id": "e30f9684"}},
+        ])
+    );
+}
+
+#[test]
+fn unknown_segments_are_preserved() {
+    let value = json!({"type": "qq.face", "data": {"id": 123}});
+    let segment = serde_json::from_value::<MessageSegment>(value.clone()).unwrap();
+    match &segment {
+        MessageSegment::Extended(extended) => {
+            assert_eq!(extended.r#type, "qq.face");
+            assert_eq!(extended.data["id"], 123);
+        }
+        other => panic!("unexpected segment: {:?}", other),
+    }
+    assert_eq!(segment.r#type(), "qq.face");
+    assert_eq!(serde_json::to_value(&segment).unwrap(), value);
+}
+
+#[test]
+fn malformed_standard_segments_are_rejected() {
+    assert!(serde_json::from_value::<MessageSegment>(json!({"type": "text", "data": {}})).is_err());
+    assert!(matches!(
+        segments_from_value(json!([{"type": "image", "data": {"file": "a.png"}}])),
+        Err(Error::BadSegmentData(_))
+    ));
+    assert!(matches!(
+        segments_from_value(json!(42)),
+        Err(Error::BadParam(_))
+    ));
+}
+
+#[test]
+fn malformed_standard_segments_report_field_errors() {
+    let err =
+        serde_json::from_value::<MessageSegment>(json!({"type": "text", "data": {"text": 1}}))
+            .unwrap_err()
+            .to_string();
+    assert!(err.contains("text 消息段参数错误"), "{}", err);
+    assert!(err.contains("invalid type: integer `1`"), "{}", err);
+
+    match segments_from_value(json!([{"type": "image", "data": {"file": "a.png"}}])) {
+        Err(Error::BadSegmentData(err)) => assert!(err.contains("file_id"), "{}", err),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn send_message_params_accept_arrays_and_strings() {
+    #[derive(Deserialize)]
+    struct SendMessageParams {
+        #[serde(deserialize_with = "deserialize_segments")]
+        message: Vec<MessageSegment>,
+    }
+
+    let params: SendMessageParams = serde_json::from_value(json!({
+        "message": [
+            {"type": "mention", "data": {"user_id": "10001"}},
+            {"type": "text", "data": {"text": " hi"}},
+        ],
+    }))
+    .unwrap();
+    assert_eq!(
+        params.message,
+        vec![
+            MessageSegment::Mention {
+                user_id: "10001".to_string()
+            },
+            MessageSegment::Text {
+                text: " hi".to_string()
+            },
+        ]
+    );
+
+    let params: SendMessageParams = serde_json::from_value(json!({"message": "hi"})).unwrap();
+    assert_eq!(
+        params.message,
+        vec![MessageSegment::Text {
+            text: "hi".to_string()
+        }]
+    );
+}
@@ -0,0 +1,93 @@
+mod common;
+
+use common::{local_listener, quiet_config, start_ws, test_onebot};
+use futures_util::StreamExt;
+use libonebot::{
+    comm::{WebSocket, HTTP},
+    Event, EventSink, Message, OneBot, User,
+};
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::{protocol::frame::coding::CloseCode, Message as WsMessage};
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn shutdown_stops_generator_and_comms() {
+    let (ws_listener, ws_port) = local_listener();
+    let (http_listener, http_port) = local_listener();
+
+    let mut onebot = OneBot::new("test");
+    onebot
+        .set_self_id("bot")
+        .set_config(quiet_config())
+        .add_comm(&"ws", WebSocket::from_listener(ws_listener).unwrap())
+        .add_comm(&"http", HTTP::from_listener(http_listener).unwrap());
+    onebot.register_event_generator(|sink: EventSink| async move {
+        let mut count = 0;
+        loop {
+            count += 1;
+            let event = Event::build(count).message(
+                Message::build(count)
+                    .text("hello")
+                    .private(User::new("user")),
+            );
+            sink.send(event).await;
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    });
+
+    let handle = onebot.run().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let (mut ws_stream, _) =
+        tokio_tungstenite::connect_async(format!("ws://127.0.0.1:{}", ws_port))
+            .await
+            .unwrap();
+    assert!(matches!(
+        ws_stream.next().await,
+        Some(Ok(WsMessage::Text(_)))
+    ));
+
+    tokio::time::timeout(Duration::from_secs(5), handle.shutdown())
+        .await
+        .expect("shutdown did not resolve");
+
+    let close = loop {
+        match ws_stream.next().await {
+            Some(Ok(WsMessage::Close(frame))) => break frame,
+            Some(Ok(_)) => continue,
+            other => panic!("expected close frame, got {:?}", other),
+        }
+    };
+    assert_eq!(close.unwrap().code, CloseCode::Away);
+
+    assert!(tokio::net::TcpStream::connect(("127.0.0.1", http_port))
+        .await
+        .is_err());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn shutdown_does_not_wait_for_clients_that_stop_reading() {
+    let mut onebot = test_onebot();
+    onebot.register_event_generator(|sink: EventSink| async move {
+        let text = "x".repeat(64 * 1024);
+        let mut count = 0;
+        loop {
+            count += 1;
+            let event = Event::build(count).message(
+                Message::build(count)
+                    .text(text.clone())
+                    .private(User::new("user")),
+            );
+            sink.send(event).await;
+            tokio::task::yield_now().await;
+        }
+    });
+    let (handle, url) = start_ws(&mut onebot, |ws| ws.disable_keepalive()).await;
+
+    // 连接后不再读取，写入任务最终阻塞在发送上
+    let (_ws_stream, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    tokio::time::timeout(Duration::from_secs(6), handle.shutdown())
+        .await
+        .expect("shutdown did not resolve");
+}
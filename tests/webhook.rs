@@ -0,0 +1,294 @@
+mod common;
+
+use common::{drain, quiet_config};
+use hmac::{Hmac, Mac};
+use libonebot::{
+    comm::{HTTPWebHook, Signature},
+    config::DefaultConfigFile,
+    ActionResponse, OneBot,
+};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::sync::mpsc;
+use warp::{
+    http::{HeaderMap, StatusCode},
+    Filter,
+};
+
+fn start_receiver() -> (String, mpsc::UnboundedReceiver<(HeaderMap, bytes::Bytes)>) {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let route = warp::post()
+        .and(warp::header::headers_cloned())
+        .and(warp::body::bytes())
+        .map(move |headers: HeaderMap, body: bytes::Bytes| {
+            let _ = sender.send((headers, body));
+            warp::reply()
+        });
+    let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+    (format!("http://{}", addr), receiver)
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn webhook_sends_onebot_headers_and_signature() {
+    let (url, mut received) = start_receiver();
+
+    let mut onebot = OneBot::new("test");
+    onebot
+        .set_self_id("bot")
+        .set_config(quiet_config())
+        .set_access_token("token")
+        .enable_heartbeat(50)
+        .add_comm(
+            &"webhook",
+            HTTPWebHook::new(url)
+                .secret("secret")
+                .signature(Signature::Sha256),
+        );
+    onebot.set_impl("test-impl", "1.2.3");
+    let handle = onebot.run().await.unwrap();
+
+    let (headers, body) = tokio::time::timeout(Duration::from_secs(2), received.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    handle.shutdown().await;
+
+    assert_eq!(headers["content-type"], "application/json");
+    assert_eq!(headers["user-agent"], "OneBot/12 (test) test-impl/1.2.3");
+    assert_eq!(headers["x-onebot-version"], "12");
+    assert_eq!(headers["x-impl"], "test-impl");
+    assert_eq!(headers["x-platform"], "test");
+    assert_eq!(headers["x-self-id"], "bot");
+    assert_eq!(headers["authorization"], "Bearer token");
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(b"secret").unwrap();
+    mac.update(&body);
+    let expected = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+    assert_eq!(headers["x-signature"], expected.as_str());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn webhook_without_secret_omits_signature() {
+    let (url, mut received) = start_receiver();
+
+    let mut onebot = OneBot::new("test");
+    onebot
+        .set_self_id("bot")
+        .set_config(quiet_config())
+        .enable_heartbeat(50)
+        .add_comm(&"webhook", HTTPWebHook::new(url));
+    let handle = onebot.run().await.unwrap();
+
+    let (headers, _) = tokio::time::timeout(Duration::from_secs(2), received.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    handle.shutdown().await;
+
+    assert!(headers.get("x-signature").is_none());
+    assert!(headers.get("authorization").is_none());
+}
+
+// 按请求次序返回给定的状态码，超出部分返回最后一个；每个请求记录事件 id
+fn start_flaky_receiver(
+    statuses: Vec<u16>,
+    delay: Duration,
+) -> (String, mpsc::UnboundedReceiver<String>) {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let count = Arc::new(AtomicUsize::new(0));
+    let route = warp::post()
+        .and(warp::body::bytes())
+        .then(move |body: bytes::Bytes| {
+            let sender = sender.clone();
+            let index = count.fetch_add(1, Ordering::SeqCst).min(statuses.len() - 1);
+            let status = StatusCode::from_u16(statuses[index]).unwrap();
+            async move {
+                let event: Value = serde_json::from_slice(&body).unwrap();
+                let _ = sender.send(event["id"].as_str().unwrap().to_string());
+                tokio::time::sleep(delay).await;
+                warp::reply::with_status(warp::reply(), status)
+            }
+        });
+    let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+    (format!("http://{}", addr), receiver)
+}
+
+async fn run_webhook(webhook: HTTPWebHook, heartbeat: u32, duration: Duration) {
+    let mut onebot = OneBot::new("test");
+    onebot
+        .set_self_id("bot")
+        .set_config(quiet_config())
+        .enable_heartbeat(heartbeat)
+        .add_comm(&"webhook", webhook);
+    let handle = onebot.run().await.unwrap();
+    tokio::time::sleep(duration).await;
+    handle.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn webhook_retries_server_errors_with_backoff() {
+    let (url, mut received) = start_flaky_receiver(vec![500, 503, 200], Duration::ZERO);
+    let webhook = HTTPWebHook::new(url)
+        .max_retries(3)
+        .retry_interval(Duration::from_millis(10));
+    run_webhook(webhook, 200, Duration::from_millis(300)).await;
+
+    let ids = drain(&mut received);
+    assert!(ids.len() >= 3);
+    assert_eq!(ids[0], ids[1]);
+    assert_eq!(ids[1], ids[2]);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn webhook_survives_failed_deliveries() {
+    let (url, mut received) = start_flaky_receiver(vec![400], Duration::ZERO);
+    let webhook = HTTPWebHook::new(url).retry_interval(Duration::from_millis(10));
+    run_webhook(webhook, 50, Duration::from_millis(300)).await;
+
+    // 4xx 不重试，且投递失败后继续投递后续事件
+    let ids = drain(&mut received);
+    assert!(ids.len() >= 2);
+    let mut unique = ids.clone();
+    unique.dedup();
+    assert_eq!(ids, unique);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn webhook_times_out_slow_receivers() {
+    let (url, mut received) = start_flaky_receiver(vec![200], Duration::from_secs(1));
+    let webhook = HTTPWebHook::new(url)
+        .timeout(Duration::from_millis(50))
+        .max_retries(1)
+        .retry_interval(Duration::from_millis(10));
+    run_webhook(webhook, 100, Duration::from_millis(400)).await;
+
+    let ids = drain(&mut received);
+    assert!(ids.len() >= 3);
+    assert_eq!(ids[0], ids[1]);
+    assert_ne!(ids[1], ids[2]);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn webhook_dispatches_quick_operations_from_response() {
+    let route = warp::post().map(|| {
+        warp::reply::json(&json!([
+            {"action": "record", "params": {"value": 1}},
+            {"action": "record", "params": {"value": 2}},
+            {"action": "unknown"},
+        ]))
+    });
+    let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+
+    let (sender, mut received) = mpsc::unbounded_channel();
+    let mut onebot = OneBot::new("test");
+    onebot
+        .set_self_id("bot")
+        .set_config(quiet_config())
+        .enable_heartbeat(1000)
+        .add_comm(&"webhook", HTTPWebHook::new(format!("http://{}", addr)));
+    onebot.register_action_handler("record", move |params: Value| {
+        let sender = sender.clone();
+        async move {
+            let _ = sender.send(params["value"].clone());
+            ActionResponse::ok(Value::Null)
+        }
+    });
+    let handle = onebot.run().await.unwrap();
+
+    let first = tokio::time::timeout(Duration::from_secs(2), received.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    let second = tokio::time::timeout(Duration::from_secs(1), received.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    handle.shutdown().await;
+
+    assert_eq!(first, json!(1));
+    assert_eq!(second, json!(2));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn webhook_keeps_delivering_while_quick_operations_run() {
+    let (sender, mut received) = mpsc::unbounded_channel();
+    let route = warp::post().map(move || {
+        let _ = sender.send(());
+        warp::reply::json(&json!([{"action": "sleep"}]))
+    });
+    let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+
+    let mut onebot = OneBot::new("test");
+    onebot
+        .set_self_id("bot")
+        .set_config(quiet_config())
+        .enable_heartbeat(50)
+        .add_comm(&"webhook", HTTPWebHook::new(format!("http://{}", addr)));
+    onebot.register_action_handler("sleep", |_: Value| async move {
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        ActionResponse::ok(Value::Null)
+    });
+    let handle = onebot.run().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(400)).await;
+
+    // 快速操作执行期间后续事件照常投递
+    let delivered = drain(&mut received).len();
+    handle.shutdown().await;
+    assert!(delivered >= 3, "only {} events were delivered", delivered);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn webhook_timeout_zero_from_config_file_disables_the_timeout() {
+    let route = warp::post().then(|| async {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        warp::reply::json(&json!([{"action": "record", "params": {"value": 1}}]))
+    });
+    let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+
+    let config_file: DefaultConfigFile = serde_json::from_value(json!({
+        "comm_method": {
+            "webhook": {
+                "type": "http_webhook",
+                "url": format!("http://{}", addr),
+                "timeout": 0,
+                "max_retries": 0,
+            },
+        },
+    }))
+    .unwrap();
+    let (sender, mut received) = mpsc::unbounded_channel();
+    let mut onebot = OneBot::new("test");
+    onebot.init_from_file(config_file).unwrap();
+    onebot
+        .set_self_id("bot")
+        .set_config(quiet_config())
+        .enable_heartbeat(1000);
+    onebot.register_action_handler("record", move |params: Value| {
+        let sender = sender.clone();
+        async move {
+            let _ = sender.send(params["value"].clone());
+            ActionResponse::ok(Value::Null)
+        }
+    });
+    let handle = onebot.run().await.unwrap();
+
+    // 响应在 200 毫秒后才返回，仍应当投递成功并执行快速操作
+    let value = tokio::time::timeout(Duration::from_secs(2), received.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    handle.shutdown().await;
+    assert_eq!(value, json!(1));
+}
@@ -0,0 +1,93 @@
+mod common;
+
+use common::{start_ws, test_onebot};
+use futures_util::{SinkExt, StreamExt};
+use libonebot::{ActionResponse, OneBotHandle};
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{tungstenite::Message, MaybeTlsStream, WebSocketStream};
+
+type Client = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+async fn connect(max_concurrent_actions: usize, heartbeat: Option<u32>) -> (OneBotHandle, Client) {
+    let mut onebot = test_onebot();
+    if let Some(heartbeat) = heartbeat {
+        onebot.enable_heartbeat(heartbeat);
+    }
+    onebot.register_action_handler("sleep", |params: Value| async move {
+        let ms = params["ms"].as_u64().unwrap_or(0);
+        tokio::time::sleep(Duration::from_millis(ms)).await;
+        ActionResponse::ok(json!(ms))
+    });
+    let (handle, url) = start_ws(&mut onebot, |ws| {
+        ws.max_concurrent_actions(max_concurrent_actions)
+    })
+    .await;
+    let (client, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+    (handle, client)
+}
+
+async fn sleep_action(client: &mut Client, ms: u64, echo: &str) {
+    let request = json!({"action": "sleep", "params": {"ms": ms}, "echo": echo});
+    client
+        .send(Message::Text(request.to_string()))
+        .await
+        .unwrap();
+}
+
+async fn next_json(client: &mut Client) -> Value {
+    loop {
+        let msg = tokio::time::timeout(Duration::from_secs(2), client.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        if let Message::Text(text) = msg {
+            return serde_json::from_str(&text).unwrap();
+        }
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn ws_runs_actions_concurrently_and_correlates_by_echo() {
+    let (handle, mut client) = connect(4, None).await;
+
+    sleep_action(&mut client, 300, "slow").await;
+    sleep_action(&mut client, 0, "fast").await;
+
+    let first = next_json(&mut client).await;
+    let second = next_json(&mut client).await;
+    assert_eq!(first["echo"], "fast");
+    assert_eq!(first["data"], 0);
+    assert_eq!(second["echo"], "slow");
+    assert_eq!(second["data"], 300);
+
+    handle.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn ws_delivers_events_while_actions_are_running() {
+    let (handle, mut client) = connect(4, Some(50)).await;
+
+    sleep_action(&mut client, 500, "slow").await;
+    let first = next_json(&mut client).await;
+    assert_eq!(first["detail_type"], "heartbeat");
+
+    handle.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn ws_bounds_concurrent_actions_per_connection() {
+    let (handle, mut client) = connect(1, None).await;
+
+    let start = Instant::now();
+    sleep_action(&mut client, 200, "first").await;
+    sleep_action(&mut client, 200, "second").await;
+
+    assert_eq!(next_json(&mut client).await["echo"], "first");
+    assert_eq!(next_json(&mut client).await["echo"], "second");
+    assert!(start.elapsed() >= Duration::from_millis(400));
+
+    handle.shutdown().await;
+}
@@ -0,0 +1,204 @@
+mod common;
+
+use common::{quiet_config, test_onebot};
+use futures_util::{SinkExt, StreamExt};
+use libonebot::{
+    comm::WebSocketReverse, Event, EventSink, Message as OneBotMessage, OneBot, OneBotHandle, User,
+};
+use serde_json::{json, Value};
+use std::{collections::HashSet, time::Duration};
+use tokio::{io::AsyncWriteExt, net::TcpListener};
+use tokio_tungstenite::tungstenite::Message;
+
+async fn start_ws_reverse(ws_reverse: WebSocketReverse, heartbeat: u32) -> OneBotHandle {
+    let mut onebot = OneBot::new("test");
+    onebot
+        .set_self_id("bot")
+        .set_config(quiet_config())
+        .enable_heartbeat(heartbeat)
+        .add_comm(&"ws_reverse", ws_reverse);
+    onebot.run().await.unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn ws_reverse_retries_until_app_is_up() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let ws_reverse = WebSocketReverse::new(format!("ws://127.0.0.1:{}", port))
+        .reconnect_interval(Duration::from_millis(50));
+    let handle = start_ws_reverse(ws_reverse, 30).await;
+
+    // 应用端尚未就绪：200 毫秒内的连接都在握手前直接断开
+    let mut refused = 0;
+    let _ = tokio::time::timeout(Duration::from_millis(200), async {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            drop(stream);
+            refused += 1;
+        }
+    })
+    .await;
+    assert!(refused >= 2, "only {} connection attempts", refused);
+    assert!(!handle.is_shutdown());
+
+    let (stream, _) = tokio::time::timeout(Duration::from_secs(1), listener.accept())
+        .await
+        .unwrap()
+        .unwrap();
+    let mut ws_stream = tokio_tungstenite::accept_async(stream).await.unwrap();
+    let msg = tokio::time::timeout(Duration::from_secs(1), ws_stream.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert!(msg.into_text().unwrap().contains("heartbeat"));
+
+    handle.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn ws_reverse_buffers_events_while_disconnected() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let ws_reverse = WebSocketReverse::new(format!("ws://127.0.0.1:{}", port))
+        .reconnect_interval(Duration::from_millis(300));
+    let handle = start_ws_reverse(ws_reverse, 20).await;
+
+    // 第一次连接收到事件后立即断开
+    let (stream, _) = listener.accept().await.unwrap();
+    let mut ws_stream = tokio_tungstenite::accept_async(stream).await.unwrap();
+    ws_stream.next().await.unwrap().unwrap();
+    drop(ws_stream);
+
+    // 重连后应当立即收到断开期间缓冲的多个事件
+    let (stream, _) = tokio::time::timeout(Duration::from_secs(2), listener.accept())
+        .await
+        .unwrap()
+        .unwrap();
+    let mut ws_stream = tokio_tungstenite::accept_async(stream).await.unwrap();
+    // 300 毫秒断开期间约产生 15 个事件，而 150 毫秒内新产生的事件不超过 8 个
+    let mut received = 0;
+    let deadline = tokio::time::sleep(Duration::from_millis(150));
+    tokio::pin!(deadline);
+    loop {
+        tokio::select! {
+            _ = &mut deadline => break,
+            msg = ws_stream.next() => match msg {
+                Some(Ok(_)) => received += 1,
+                _ => break,
+            },
+        }
+    }
+    assert!(received >= 12, "only {} events were received", received);
+
+    handle.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn ws_reverse_split_routes_events_and_actions_separately() {
+    let event_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let api_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let ws_reverse = WebSocketReverse::split(
+        format!("ws://{}", event_listener.local_addr().unwrap()),
+        format!("ws://{}", api_listener.local_addr().unwrap()),
+    );
+    let handle = start_ws_reverse(ws_reverse, 30).await;
+
+    let (stream, _) = event_listener.accept().await.unwrap();
+    let mut event_stream = tokio_tungstenite::accept_async(stream).await.unwrap();
+    let (stream, _) = api_listener.accept().await.unwrap();
+    let mut api_stream = tokio_tungstenite::accept_async(stream).await.unwrap();
+
+    let msg = tokio::time::timeout(Duration::from_secs(1), event_stream.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert!(msg.into_text().unwrap().contains("heartbeat"));
+
+    api_stream
+        .send(Message::Text(
+            json!({"action": "get_version", "echo": 1}).to_string(),
+        ))
+        .await
+        .unwrap();
+    let response: Value = serde_json::from_str(
+        &tokio::time::timeout(Duration::from_secs(1), api_stream.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap()
+            .into_text()
+            .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(response["status"], "ok");
+    assert_eq!(response["echo"], 1);
+
+    // API 连接上不会推送事件
+    let msg = tokio::time::timeout(Duration::from_millis(100), api_stream.next()).await;
+    assert!(msg.is_err());
+
+    handle.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn ws_reverse_resends_events_queued_when_the_connection_drops() {
+    const EVENTS: usize = 200;
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let mut onebot = test_onebot();
+    onebot.register_event_generator(|sink: EventSink| async move {
+        let text = "x".repeat(64 * 1024);
+        for id in 0..EVENTS {
+            let event = Event::build(id).message(
+                OneBotMessage::build(id)
+                    .text(text.clone())
+                    .private(User::new("user")),
+            );
+            sink.send(event).await;
+        }
+        Ok(())
+    });
+    onebot.add_comm(
+        &"ws_reverse",
+        WebSocketReverse::new(format!("ws://127.0.0.1:{}", port))
+            .reconnect_interval(Duration::from_millis(100))
+            .buffer_size(EVENTS)
+            .disable_keepalive(),
+    );
+    let handle = onebot.run().await.unwrap();
+
+    // 第一次连接先不读取，等写入队列堆满后关闭写方向使连接断开，再读完已经写入的事件
+    let (stream, _) = listener.accept().await.unwrap();
+    let mut ws_stream = tokio_tungstenite::accept_async(stream).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    ws_stream.get_mut().shutdown().await.unwrap();
+    let mut ids = HashSet::new();
+    while let Some(Ok(msg)) = ws_stream.next().await {
+        ids.insert(event_id(msg));
+    }
+    assert!(ids.len() < EVENTS);
+
+    // 写入队列中尚未写入的事件应当在重连后补发
+    let (stream, _) = tokio::time::timeout(Duration::from_secs(2), listener.accept())
+        .await
+        .unwrap()
+        .unwrap();
+    let mut ws_stream = tokio_tungstenite::accept_async(stream).await.unwrap();
+    while ids.len() < EVENTS {
+        let msg = tokio::time::timeout(Duration::from_secs(2), ws_stream.next())
+            .await
+            .unwrap_or_else(|_| panic!("only {} of {} events arrived", ids.len(), EVENTS))
+            .unwrap()
+            .unwrap();
+        ids.insert(event_id(msg));
+    }
+
+    handle.shutdown().await;
+}
+
+fn event_id(msg: Message) -> String {
+    let event: Value = serde_json::from_str(&msg.into_text().unwrap()).unwrap();
+    event["id"].as_str().unwrap().to_string()
+}